@@ -0,0 +1,128 @@
+//spectrum analyser for the editor. the audio thread drops the input and the tape return into a pair
+//of lock-free ring buffers, the editor takes a snapshot of them each frame and runs the FFTs itself
+
+use atomic_float::AtomicF32;
+use realfft::num_complex::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub const FFT_SIZE: usize = 2048;
+//how much of the previous frame's spectrum is kept, makes the display a lot easier to read
+const SPECTRUM_SMOOTHING: f32 = 0.7;
+
+pub struct AnalyzerData {
+    pub input: [AtomicF32; FFT_SIZE],
+    pub tape_return: [AtomicF32; FFT_SIZE],
+    write_pos: AtomicUsize,
+    pub sample_rate: AtomicF32,
+}
+
+impl Default for AnalyzerData {
+    fn default() -> Self {
+        Self {
+            input: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            tape_return: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            write_pos: AtomicUsize::new(0),
+            sample_rate: AtomicF32::new(44100.0),
+        }
+    }
+}
+
+impl AnalyzerData {
+    /// Called from the audio thread once per frame.
+    pub fn push(&self, input: f32, tape_return: f32) {
+        let pos = self.write_pos.load(Ordering::Relaxed);
+
+        self.input[pos].store(input, Ordering::Relaxed);
+        self.tape_return[pos].store(tape_return, Ordering::Relaxed);
+        self.write_pos
+            .store((pos + 1) % FFT_SIZE, Ordering::Relaxed);
+    }
+}
+
+/// The editor's half, owns the FFT and the smoothed spectra in dB. Bin `n` sits at
+/// `n * sample_rate / FFT_SIZE` Hz.
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    scratch: Vec<f32>,
+    bins: Vec<Complex<f32>>,
+    pub input_db: Vec<f32>,
+    pub tape_return_db: Vec<f32>,
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        let scratch = fft.make_input_vec();
+        let bins = fft.make_output_vec();
+        let num_bins = bins.len();
+
+        Self {
+            fft,
+            //hann
+            window: (0..FFT_SIZE)
+                .map(|i| 0.5 - 0.5 * f32::cos(2.0 * PI * i as f32 / FFT_SIZE as f32))
+                .collect(),
+            scratch,
+            bins,
+            input_db: vec![-120.0; num_bins],
+            tape_return_db: vec![-120.0; num_bins],
+        }
+    }
+}
+
+impl SpectrumAnalyzer {
+    pub fn update(&mut self, data: &AnalyzerData) {
+        let start = data.write_pos.load(Ordering::Relaxed);
+
+        Self::analyse(
+            &self.fft,
+            &self.window,
+            &mut self.scratch,
+            &mut self.bins,
+            &data.input,
+            start,
+            &mut self.input_db,
+        );
+        Self::analyse(
+            &self.fft,
+            &self.window,
+            &mut self.scratch,
+            &mut self.bins,
+            &data.tape_return,
+            start,
+            &mut self.tape_return_db,
+        );
+    }
+
+    fn analyse(
+        fft: &Arc<dyn RealToComplex<f32>>,
+        window: &[f32],
+        scratch: &mut [f32],
+        bins: &mut [Complex<f32>],
+        ring: &[AtomicF32; FFT_SIZE],
+        start: usize,
+        spectrum_db: &mut [f32],
+    ) {
+        //oldest sample first
+        for (i, (sample, window)) in scratch.iter_mut().zip(window.iter()).enumerate() {
+            *sample = ring[(start + i) % FFT_SIZE].load(Ordering::Relaxed) * window;
+        }
+
+        if fft.process(scratch, bins).is_err() {
+            return;
+        }
+
+        //the hann window halves the amplitude, scale so a full scale sine reads 0 dB
+        let scale = 4.0 / FFT_SIZE as f32;
+        for (db, bin) in spectrum_db.iter_mut().zip(bins.iter()) {
+            let magnitude = (bin.norm() * scale).max(1e-6);
+            let new_db = 20.0 * magnitude.log10();
+
+            *db = *db * SPECTRUM_SMOOTHING + new_db * (1.0 - SPECTRUM_SMOOTHING);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//biquad filter with coefficients from the RBJ audio EQ cookbook:
+//https://www.w3.org/TR/audio-eq-cookbook/
+
+use std::f32::consts::PI;
+
+pub const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+#[derive(Clone, Copy)]
+pub struct BIQUAD {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+
+    //transposed direct form II state, holds up fine when the coefficients move every sample
+    z1: f32,
+    z2: f32,
+}
+
+impl Default for BIQUAD {
+    //passes everything through untouched until it's given some coefficients
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
+
+impl BIQUAD {
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let out = self.b0 * sample + self.z1;
+        self.z1 = self.b1 * sample - self.a1 * out + self.z2;
+        self.z2 = self.b2 * sample - self.a2 * out;
+
+        return out;
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    pub fn set_lowpass(&mut self, samplerate: f32, frequency: f32, q: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+
+        self.set_coefficients(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    pub fn set_highpass(&mut self, samplerate: f32, frequency: f32, q: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+
+        self.set_coefficients(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    pub fn set_allpass(&mut self, samplerate: f32, frequency: f32, q: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+
+        self.set_coefficients(
+            1.0 - alpha,
+            -2.0 * cos,
+            1.0 + alpha,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        );
+    }
+
+    pub fn set_notch(&mut self, samplerate: f32, frequency: f32, q: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+
+        self.set_coefficients(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+    }
+
+    pub fn set_peak(&mut self, samplerate: f32, frequency: f32, q: f32, gain_db: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+        let a = 10.0_f32.powf(gain_db / 40.0);
+
+        self.set_coefficients(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        );
+    }
+
+    pub fn set_lowshelf(&mut self, samplerate: f32, frequency: f32, q: f32, gain_db: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+        let a = 10.0_f32.powf(gain_db / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        self.set_coefficients(
+            a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+            (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+        );
+    }
+
+    pub fn set_highshelf(&mut self, samplerate: f32, frequency: f32, q: f32, gain_db: f32) {
+        let (cos, alpha) = Self::omega(samplerate, frequency, q);
+        let a = 10.0_f32.powf(gain_db / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        self.set_coefficients(
+            a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+            (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+        );
+    }
+
+    //cos(w0) and alpha, the two terms every cookbook filter is built from
+    fn omega(samplerate: f32, frequency: f32, q: f32) -> (f32, f32) {
+        //keep clear of nyquist where the coefficients stop making sense
+        let frequency = f32::clamp(frequency, 1.0, samplerate * 0.49);
+        let w0 = 2.0 * PI * frequency / samplerate;
+
+        return (f32::cos(w0), f32::sin(w0) / (2.0 * q));
+    }
+
+    fn set_coefficients(&mut self, b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) {
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+}
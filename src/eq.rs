@@ -1,37 +1,91 @@
 //translated from the C++ example here: https://www.musicdsp.org/en/latest/Filters/236-3-band-equaliser.html
 
+use crate::biquad::{BIQUAD, BUTTERWORTH_Q};
+use crate::NUM_CHANNELS;
+use nih_plug::prelude::Enum;
 use std::f32::consts::PI;
 
 const VSA: f32 = f32::EPSILON;
-const LOWFREQ: f32 = 880.0;
-const HIGHFREQ: f32 = 5000.0;
+pub const LOWFREQ: f32 = 880.0;
+pub const HIGHFREQ: f32 = 5000.0;
 
+/// How the signal gets split into bands. The one pole cascade is the original musicdsp EQ, the
+/// Linkwitz-Riley crossover costs a bit more but its bands add back up to a flat response.
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum Crossover {
+    #[id = "one pole"]
+    #[name = "one pole"]
+    OnePole,
+    #[id = "lr4"]
+    #[name = "LR4"]
+    LinkwitzRiley,
+}
+
+/// Where the EQ sits in the signal path. On the input it colours everything including the dry
+/// signal, on the tape return only the loop, and in the feedback loop it shapes what's on the tape
+/// a little more on every pass.
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum EqPlacement {
+    #[id = "input"]
+    #[name = "input"]
+    Input,
+    #[id = "tape return"]
+    #[name = "tape return"]
+    TapeReturn,
+    #[id = "feedback loop"]
+    #[name = "feedback loop"]
+    FeedbackLoop,
+}
+
+/// The filter state is kept per channel, the settings are shared.
 #[derive(Clone, Copy)]
 pub struct EQSTATE {
-    //filter 1
+    //filter 1. the low crossover gives way to the high one, with them crossed the one pole mid band
+    //comes out inverted
+    low_freq: f32,
+    low_request: f32,
     lf: f32,
-    f1p0: f32,
-    f1p1: f32,
-    f1p2: f32,
-    f1p3: f32,
+    f1p0: [f32; NUM_CHANNELS],
+    f1p1: [f32; NUM_CHANNELS],
+    f1p2: [f32; NUM_CHANNELS],
+    f1p3: [f32; NUM_CHANNELS],
 
     //filter2
+    high_freq: f32,
     hf: f32,
-    f2p0: f32,
-    f2p1: f32,
-    f2p2: f32,
-    f2p3: f32,
+    f2p0: [f32; NUM_CHANNELS],
+    f2p1: [f32; NUM_CHANNELS],
+    f2p2: [f32; NUM_CHANNELS],
+    f2p3: [f32; NUM_CHANNELS],
 
     //sample history
-    sdm1: f32,
-    sdm2: f32,
-    sdm3: f32,
+    sdm1: [f32; NUM_CHANNELS],
+    sdm2: [f32; NUM_CHANNELS],
+    sdm3: [f32; NUM_CHANNELS],
+
+    //the one pole split takes its mid and high off the input from three samples back, which is
+    //fine on the way through but shifts the loop along by three frames on every pass when it sits
+    //in the feedback loop. this takes them off the current sample instead
+    pub zero_latency: bool,
+
+    //linkwitz-riley crossover, each LR4 filter is a pair of butterworth biquads. the low band goes
+    //through an allpass at the high crossover so its phase lines up with the other two
+    pub crossover: Crossover,
+    lr_low: [[BIQUAD; 2]; NUM_CHANNELS],
+    lr_rest: [[BIQUAD; 2]; NUM_CHANNELS],
+    lr_mid: [[BIQUAD; 2]; NUM_CHANNELS],
+    lr_high: [[BIQUAD; 2]; NUM_CHANNELS],
+    lr_low_allpass: [BIQUAD; NUM_CHANNELS],
 
     //gain controls
     pub lg: f32,
     pub mg: f32,
     pub hg: f32,
 
+    //low, mid, high. if anything is soloed only soloed bands pass, mutes win over solos
+    pub solo: [bool; 3],
+    pub mute: [bool; 3],
+
     //samplerate
     pub sr: f32,
 }
@@ -39,26 +93,41 @@ pub struct EQSTATE {
 impl Default for EQSTATE {
     fn default() -> Self {
         Self {
+            low_freq: LOWFREQ,
+            low_request: LOWFREQ,
             lf: 0.0,
-            f1p0: 0.0,
-            f1p1: 0.0,
-            f1p2: 0.0,
-            f1p3: 0.0,
+            f1p0: [0.0; NUM_CHANNELS],
+            f1p1: [0.0; NUM_CHANNELS],
+            f1p2: [0.0; NUM_CHANNELS],
+            f1p3: [0.0; NUM_CHANNELS],
 
+            high_freq: HIGHFREQ,
             hf: 0.0,
-            f2p0: 0.0,
-            f2p1: 0.0,
-            f2p2: 0.0,
-            f2p3: 0.0,
+            f2p0: [0.0; NUM_CHANNELS],
+            f2p1: [0.0; NUM_CHANNELS],
+            f2p2: [0.0; NUM_CHANNELS],
+            f2p3: [0.0; NUM_CHANNELS],
+
+            sdm1: [0.0; NUM_CHANNELS],
+            sdm2: [0.0; NUM_CHANNELS],
+            sdm3: [0.0; NUM_CHANNELS],
 
-            sdm1: 0.0,
-            sdm2: 0.0,
-            sdm3: 0.0,
+            zero_latency: false,
+
+            crossover: Crossover::OnePole,
+            lr_low: [[BIQUAD::default(); 2]; NUM_CHANNELS],
+            lr_rest: [[BIQUAD::default(); 2]; NUM_CHANNELS],
+            lr_mid: [[BIQUAD::default(); 2]; NUM_CHANNELS],
+            lr_high: [[BIQUAD::default(); 2]; NUM_CHANNELS],
+            lr_low_allpass: [BIQUAD::default(); NUM_CHANNELS],
 
             lg: 1.0,
             mg: 1.0,
             hg: 1.0,
 
+            solo: [false; 3],
+            mute: [false; 3],
+
             sr: 44100.0,
         }
     }
@@ -72,56 +141,250 @@ impl EQSTATE {
         self.mg = 1.0;
         self.hg = 1.0;
 
-        self.set_highband_frequency(HIGHFREQ);
-        self.set_lowband_frequency(LOWFREQ);
+        self.lf = self.calculate_bandpass_frequency(self.low_freq);
+        self.hf = self.calculate_bandpass_frequency(self.high_freq);
+        self.update_low_crossover();
+        self.update_high_crossover();
     }
 
+    //the coefficients don't carry any filter state so these are safe to call every sample, they
+    //only do the trig when the frequency actually moves
     pub fn set_lowband_frequency(&mut self, frequency: f32) {
-        self.lf = self.calculate_bandpass_frequency(frequency);
+        self.low_request = frequency;
+        self.update_low_frequency();
     }
 
     pub fn set_highband_frequency(&mut self, frequency: f32) {
-        self.hf = self.calculate_bandpass_frequency(frequency)
+        if frequency != self.high_freq {
+            self.high_freq = frequency;
+            self.hf = self.calculate_bandpass_frequency(frequency);
+            self.update_high_crossover();
+        }
+        self.update_low_frequency();
     }
 
-    fn calculate_bandpass_frequency(&self, frequency: f32) -> f32 {
-        return 2.0 * f32::sin(PI * (frequency / self.sr));
+    fn update_low_frequency(&mut self) {
+        let frequency = self.low_request.min(self.high_freq);
+        if frequency != self.low_freq {
+            self.low_freq = frequency;
+            self.lf = self.calculate_bandpass_frequency(frequency);
+            self.update_low_crossover();
+        }
     }
 
-    pub fn process_3band<'a>(&'a mut self, sample: &'a mut f32) {
-        let mut l: f32;
-        let mut m: f32;
-        let mut h: f32;
+    fn update_low_crossover(&mut self) {
+        for filter in self.lr_low.iter_mut().flatten() {
+            filter.set_lowpass(self.sr, self.low_freq, BUTTERWORTH_Q);
+        }
+        for filter in self.lr_rest.iter_mut().flatten() {
+            filter.set_highpass(self.sr, self.low_freq, BUTTERWORTH_Q);
+        }
+    }
 
-        //lowpass
-        self.f1p0 += (self.lf * (*sample - self.f1p0)) + VSA;
-        self.f1p1 += self.lf * (self.f1p0 - self.f1p1);
-        self.f1p2 += self.lf * (self.f1p1 - self.f1p2);
-        self.f1p3 += self.lf * (self.f1p2 - self.f1p3);
+    fn update_high_crossover(&mut self) {
+        for filter in self.lr_mid.iter_mut().flatten() {
+            filter.set_lowpass(self.sr, self.high_freq, BUTTERWORTH_Q);
+        }
+        for filter in self.lr_high.iter_mut().flatten() {
+            filter.set_highpass(self.sr, self.high_freq, BUTTERWORTH_Q);
+        }
+        for filter in self.lr_low_allpass.iter_mut() {
+            filter.set_allpass(self.sr, self.high_freq, BUTTERWORTH_Q);
+        }
+    }
 
-        l = self.f1p3;
+    fn calculate_bandpass_frequency(&self, frequency: f32) -> f32 {
+        //keep clear of nyquist, the one pole cascade blows up past it
+        let frequency = f32::min(frequency, self.sr * 0.45);
+        return 2.0 * f32::sin(PI * (frequency / self.sr));
+    }
 
-        //highpass
-        self.f2p0 += (self.hf * (*sample - self.f2p0)) + VSA;
-        self.f2p1 += self.hf * (self.f2p0 - self.f2p1);
-        self.f2p2 += self.hf * (self.f2p1 - self.f2p2);
-        self.f2p3 += self.hf * (self.f2p2 - self.f2p3);
+    pub fn process_3band<'a>(&'a mut self, channel: usize, sample: &'a mut f32) {
+        let bands = self.split_3band(channel, *sample);
+        let (l, m, h) = self.weigh_bands(bands);
 
-        h = self.sdm3 - self.f2p3;
+        *sample = l + m + h;
+    }
 
-        //calc midrange (signal - (low + high))
-        m = self.sdm3 - (h + l);
+    /// Apply the band gains, solos and mutes to a split signal.
+    pub fn weigh_bands(&self, bands: (f32, f32, f32)) -> (f32, f32, f32) {
+        let (mut l, mut m, mut h) = bands;
 
         //scale combine and store
         l *= self.lg;
         m *= self.mg;
         h *= self.hg;
 
+        let any_solo = self.solo.contains(&true);
+        for (band, (solo, mute)) in [&mut l, &mut m, &mut h]
+            .into_iter()
+            .zip(self.solo.iter().zip(self.mute.iter()))
+        {
+            if *mute || (any_solo && !*solo) {
+                *band = 0.0;
+            }
+        }
+
+        return (l, m, h);
+    }
+
+    /// Split a sample from `channel` into its low, mid and high bands without applying any of the
+    /// gains.
+    pub fn split_3band(&mut self, channel: usize, sample: f32) -> (f32, f32, f32) {
+        match self.crossover {
+            Crossover::OnePole => self.split_one_pole(channel, sample),
+            Crossover::LinkwitzRiley => self.split_linkwitz_riley(channel, sample),
+        }
+    }
+
+    fn split_one_pole(&mut self, c: usize, sample: f32) -> (f32, f32, f32) {
+        let l: f32;
+        let m: f32;
+        let h: f32;
+
+        //lowpass
+        self.f1p0[c] += (self.lf * (sample - self.f1p0[c])) + VSA;
+        self.f1p1[c] += self.lf * (self.f1p0[c] - self.f1p1[c]);
+        self.f1p2[c] += self.lf * (self.f1p1[c] - self.f1p2[c]);
+        self.f1p3[c] += self.lf * (self.f1p2[c] - self.f1p3[c]);
+
+        l = self.f1p3[c];
+
+        //highpass
+        self.f2p0[c] += (self.hf * (sample - self.f2p0[c])) + VSA;
+        self.f2p1[c] += self.hf * (self.f2p0[c] - self.f2p1[c]);
+        self.f2p2[c] += self.hf * (self.f2p1[c] - self.f2p2[c]);
+        self.f2p3[c] += self.hf * (self.f2p2[c] - self.f2p3[c]);
+
+        let dry = if self.zero_latency {
+            sample
+        } else {
+            self.sdm3[c]
+        };
+
+        h = dry - self.f2p3[c];
+
+        //calc midrange (signal - (low + high))
+        m = dry - (h + l);
+
         //shuffle history buffer
-        self.sdm3 = self.sdm2;
-        self.sdm2 = self.sdm1;
-        self.sdm1 = sample.clone();
+        self.sdm3[c] = self.sdm2[c];
+        self.sdm2[c] = self.sdm1[c];
+        self.sdm1[c] = sample;
 
-        *sample = l + m + h;
+        return (l, m, h);
+    }
+
+    fn split_linkwitz_riley(&mut self, c: usize, sample: f32) -> (f32, f32, f32) {
+        let mut l = sample;
+        let mut rest = sample;
+        for (low, high) in self.lr_low[c].iter_mut().zip(self.lr_rest[c].iter_mut()) {
+            l = low.process(l);
+            rest = high.process(rest);
+        }
+        l = self.lr_low_allpass[c].process(l);
+
+        let mut m = rest;
+        let mut h = rest;
+        for (low, high) in self.lr_mid[c].iter_mut().zip(self.lr_high[c].iter_mut()) {
+            m = low.process(m);
+            h = high.process(h);
+        }
+
+        return (l, m, h);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const IMPULSE_LEN: usize = 16384;
+
+    //magnitude of an impulse response at `frequency`, straight off the DFT sum
+    fn magnitude(response: &[f32], frequency: f32) -> f32 {
+        let (mut re, mut im) = (0.0_f64, 0.0_f64);
+        for (n, sample) in response.iter().enumerate() {
+            let phase =
+                -2.0 * std::f64::consts::PI * frequency as f64 * n as f64 / SAMPLE_RATE as f64;
+            re += *sample as f64 * phase.cos();
+            im += *sample as f64 * phase.sin();
+        }
+
+        return (re * re + im * im).sqrt() as f32;
+    }
+
+    fn linkwitz_riley(low: f32, high: f32) -> EQSTATE {
+        let mut eq = EQSTATE::default();
+        eq.crossover = Crossover::LinkwitzRiley;
+        eq.init(SAMPLE_RATE);
+        eq.set_lowband_frequency(low);
+        eq.set_highband_frequency(high);
+
+        return eq;
+    }
+
+    #[test]
+    fn linkwitz_riley_bands_sum_flat() {
+        for (low, high) in [(LOWFREQ, HIGHFREQ), (200.0, 2000.0), (1000.0, 1200.0)] {
+            let mut eq = linkwitz_riley(low, high);
+            let response: Vec<f32> = (0..IMPULSE_LEN)
+                .map(|n| {
+                    let (l, m, h) = eq.split_3band(0, if n == 0 { 1.0 } else { 0.0 });
+                    l + m + h
+                })
+                .collect();
+
+            //20 Hz to 20 kHz in sixth octaves
+            for step in 0..60 {
+                let frequency = 20.0 * 2.0_f32.powf(step as f32 / 6.0);
+                let gain = magnitude(&response, frequency);
+                assert!(
+                    (gain - 1.0).abs() < 1e-3,
+                    "{low}/{high} Hz crossover sums to {gain} at {frequency} Hz"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn zero_latency_one_pole_passes_flat_gains_straight_through() {
+        let mut eq = EQSTATE::default();
+        eq.init(SAMPLE_RATE);
+        eq.zero_latency = true;
+
+        for n in 0..64 {
+            let mut sample = if n == 0 { 1.0 } else { 0.0 };
+            let input = sample;
+            eq.process_3band(0, &mut sample);
+            assert!(
+                (sample - input).abs() < 1e-6,
+                "frame {n} came out as {sample}"
+            );
+        }
+    }
+
+    #[test]
+    fn low_crossover_stays_below_high() {
+        let mut eq = linkwitz_riley(1500.0, 1200.0);
+        assert_eq!(eq.low_freq, 1200.0);
+
+        eq.set_highband_frequency(3000.0);
+        assert_eq!(eq.low_freq, 1500.0);
+    }
+
+    #[test]
+    fn channels_dont_share_state() {
+        let mut eq = linkwitz_riley(LOWFREQ, HIGHFREQ);
+
+        //an impulse on the left, silence on the right, interleaved like the process loop
+        for n in 0..1024 {
+            let (l, m, h) = eq.split_3band(0, if n == 0 { 1.0 } else { 0.0 });
+            assert!(n > 0 || l + m + h != 0.0);
+
+            let (l, m, h) = eq.split_3band(1, 0.0);
+            assert_eq!(l + m + h, 0.0);
+        }
     }
 }
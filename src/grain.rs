@@ -0,0 +1,126 @@
+//granular playback over the tape loop, grains are read straight out of TAPESTATE's buffer
+
+use crate::rng::XORSHIFT;
+use crate::tapeloop::TAPESTATE;
+use std::f32::consts::PI;
+
+const MAX_GRAINS: usize = 32;
+
+#[derive(Clone, Copy)]
+struct GRAIN {
+    active: bool,
+    //read position in the loop, in frames
+    position: f64,
+    //frames to move per step, negative when the grain plays backwards
+    increment: f64,
+    age: usize,
+    length: usize,
+}
+
+impl Default for GRAIN {
+    fn default() -> Self {
+        Self {
+            active: false,
+            position: 0.0,
+            increment: 1.0,
+            age: 0,
+            length: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GRAINSTATE {
+    grains: [GRAIN; MAX_GRAINS],
+    samplerate: f32,
+    //frames left until the next grain is spawned
+    countdown: f32,
+    rng: XORSHIFT,
+
+    //grain controls
+    pub size_ms: f32,
+    pub density: f32,
+    pub spray_ms: f32,
+    pub pitch: f32,
+    pub reverse_chance: f32,
+}
+
+impl Default for GRAINSTATE {
+    fn default() -> Self {
+        Self {
+            grains: [GRAIN::default(); MAX_GRAINS],
+            samplerate: 44100.0,
+            countdown: 0.0,
+            rng: XORSHIFT::new(0x9E37_79B9),
+
+            size_ms: 100.0,
+            density: 20.0,
+            spray_ms: 50.0,
+            pitch: 0.0,
+            reverse_chance: 0.0,
+        }
+    }
+}
+
+impl GRAINSTATE {
+    pub fn init(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.countdown = 0.0;
+        self.grains = [GRAIN::default(); MAX_GRAINS];
+    }
+
+    /// Age every grain by a frame and spawn the next one when it's due, called once per frame
+    /// before the channels are read.
+    pub fn advance(&mut self, tape: &TAPESTATE) {
+        for grain in self.grains.iter_mut().filter(|grain| grain.active) {
+            grain.position += grain.increment;
+            grain.age += 1;
+            if grain.age >= grain.length {
+                grain.active = false;
+            }
+        }
+
+        self.countdown -= 1.0;
+        if self.countdown <= 0.0 {
+            self.spawn(tape);
+            self.countdown += self.samplerate / self.density.max(0.1);
+        }
+    }
+
+    pub fn read(&self, tape: &TAPESTATE, channel: usize) -> f32 {
+        let mut out = 0.0;
+        for grain in self.grains.iter().filter(|grain| grain.active) {
+            //hann window over the grain's lifetime
+            let phase = grain.age as f32 / grain.length as f32;
+            let window = 0.5 - 0.5 * f32::cos(2.0 * PI * phase);
+
+            out += tape.sample_at(grain.position, channel) * window;
+        }
+
+        //keep the level roughly constant however many grains overlap
+        let overlap = self.density * self.size_ms / 1000.0;
+        return out / f32::sqrt(overlap.max(1.0));
+    }
+
+    fn spawn(&mut self, tape: &TAPESTATE) {
+        let length = (self.size_ms / 1000.0 * self.samplerate) as usize;
+        if length < 2 {
+            return;
+        }
+
+        let spray = (self.rng.random() * 2.0 - 1.0) * self.spray_ms / 1000.0 * self.samplerate;
+        let reverse = self.rng.random() < self.reverse_chance;
+        let rate = 2.0_f32.powf(self.pitch / 12.0) as f64;
+
+        //if every voice is busy the new grain is simply dropped
+        if let Some(grain) = self.grains.iter_mut().find(|grain| !grain.active) {
+            *grain = GRAIN {
+                active: true,
+                position: tape.playhead() + spray as f64,
+                increment: if reverse { -rate } else { rate },
+                age: 0,
+                length,
+            };
+        }
+    }
+}
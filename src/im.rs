@@ -1,5 +1,5 @@
 use nih_plug::nih_dbg;
-use nih_plug_egui::egui::ColorImage;
+use nih_plug_egui::egui::{self, ColorImage, TextureHandle};
 
 const BG_IMAGE: &[u8] = include_bytes!("../Resources/background.png");
 const REEL_R_IMAGE: &[u8] = include_bytes!("../Resources/reel_r.png");
@@ -22,8 +22,29 @@ impl Default for UiImages {
     }
 }
 
+/// The images uploaded to the GPU, done once when the editor opens rather than every frame.
+pub struct UiTextures {
+    pub background: TextureHandle,
+    pub reel_l: TextureHandle,
+    pub reel_r: TextureHandle,
+}
+
+impl UiTextures {
+    pub fn load(ctx: &egui::Context, images: &UiImages) -> Self {
+        let load = |name: &str, image: &ColorImage| {
+            ctx.load_texture(name, image.to_owned(), egui::TextureFilter::Linear)
+        };
+
+        Self {
+            background: load("background", &images.background),
+            reel_l: load("reel_l", &images.reel_l),
+            reel_r: load("reel_r", &images.reel_r),
+        }
+    }
+}
+
 pub fn load_image_from_memory(img: &[u8]) -> Result<ColorImage, image::ImageError> {
-    let image = image::load_from_memory(img).expect("couldn't load");
+    let image = image::load_from_memory(img)?;
     let size = [image.width() as _, image.height() as _];
     let image_buffer = image.to_rgba8();
     let pixels = image_buffer.as_flat_samples();
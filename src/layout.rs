@@ -0,0 +1,79 @@
+//maps the editor's design coordinates onto whatever size the window actually is. everything in the
+//editor is laid out against a 512x256 design and scaled from there, letterboxed if the window's
+//aspect ratio doesn't match
+
+use nih_plug_egui::egui;
+
+pub const EDITOR_WIDTH: f32 = 512.0;
+pub const EDITOR_HEIGHT: f32 = 256.0;
+pub const MIN_SCALE: f32 = 1.0;
+pub const MAX_SCALE: f32 = 3.0;
+//what the scale menu offers, the persisted value can be anything in MIN_SCALE..=MAX_SCALE
+pub const SCALE_STEPS: [f32; 5] = [1.0, 1.5, 2.0, 2.5, 3.0];
+
+/// Window size in logical pixels for a scale factor.
+pub fn window_size(scale: f32) -> (u32, u32) {
+    let scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+    (
+        (EDITOR_WIDTH * scale).round() as u32,
+        (EDITOR_HEIGHT * scale).round() as u32,
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub scale: f32,
+    origin: egui::Pos2,
+}
+
+impl Layout {
+    /// Fit the design into `rect`, keeping its aspect ratio.
+    pub fn fit(rect: egui::Rect) -> Self {
+        let scale = f32::min(rect.width() / EDITOR_WIDTH, rect.height() / EDITOR_HEIGHT);
+        let used = egui::vec2(EDITOR_WIDTH, EDITOR_HEIGHT) * scale;
+
+        Self {
+            scale,
+            origin: rect.center() - used * 0.5,
+        }
+    }
+
+    pub fn pos(&self, x: f32, y: f32) -> egui::Pos2 {
+        self.origin + egui::vec2(x, y) * self.scale
+    }
+
+    pub fn size(&self, width: f32, height: f32) -> egui::Vec2 {
+        egui::vec2(width, height) * self.scale
+    }
+
+    pub fn rect(&self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> egui::Rect {
+        egui::Rect::from_min_max(self.pos(min_x, min_y), self.pos(max_x, max_y))
+    }
+
+    pub fn rect_from_center_size(&self, x: f32, y: f32, width: f32, height: f32) -> egui::Rect {
+        egui::Rect::from_center_size(self.pos(x, y), self.size(width, height))
+    }
+
+    /// The whole design area.
+    pub fn full_rect(&self) -> egui::Rect {
+        self.rect(0.0, 0.0, EDITOR_WIDTH, EDITOR_HEIGHT)
+    }
+
+    /// Scale text and the default widget metrics to match. Only needs doing when the scale
+    /// changes, egui keeps the style around.
+    pub fn apply_style(&self, ctx: &egui::Context) {
+        let mut style = egui::Style::default();
+
+        for font in style.text_styles.values_mut() {
+            font.size *= self.scale;
+        }
+        style.spacing.item_spacing *= self.scale;
+        style.spacing.button_padding *= self.scale;
+        style.spacing.interact_size *= self.scale;
+        style.spacing.icon_width *= self.scale;
+        style.spacing.icon_spacing *= self.scale;
+        style.spacing.slider_width = 130.0 * self.scale;
+
+        ctx.set_style(style);
+    }
+}
@@ -1,29 +1,107 @@
+mod analyzer;
+pub use crate::analyzer::{AnalyzerData, SpectrumAnalyzer};
+mod biquad;
+pub use crate::biquad::BIQUAD;
 mod eq;
-pub use crate::eq::EQSTATE;
+pub use crate::eq::{Crossover, EqPlacement, EQSTATE, HIGHFREQ, LOWFREQ};
+mod grain;
+pub use crate::grain::GRAINSTATE;
 mod im;
-pub use crate::im::UiImages;
+pub use crate::im::{UiImages, UiTextures};
+mod layout;
+pub use crate::layout::Layout;
+mod meter;
+pub use crate::meter::{MeterData, METERSTATE};
+mod morph;
+pub use crate::morph::{Snapshots, MORPHSTATE};
+mod parametric;
+mod paths;
+mod presets;
+pub use crate::parametric::{BandType, NUM_BANDS, PARAMETRICSTATE};
+pub use crate::presets::{Preset, PresetBrowser, TapeTransfer};
+mod rng;
+mod shortcuts;
+pub use crate::shortcuts::Shortcuts;
+mod skin;
+pub use crate::skin::{Skin, SkinPositions};
+mod slice;
+pub use crate::slice::{SliceDivision, SliceMode, SlicePattern, SLICESTATE};
+mod svf;
+pub use crate::svf::{FilterMode, LfoDivision, SVFSTATE};
 mod tapeloop;
-pub use crate::tapeloop::TAPESTATE;
+pub use crate::tapeloop::{HeadSelect, SpeedOctave, NUM_HEADS, TAPESTATE};
+mod waveform;
+pub use crate::waveform::{WaveformData, WAVEFORMSTATE};
+mod widgets;
 use nih_plug::prelude::*;
 use nih_plug_egui::{create_egui_editor, egui, EguiState};
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, RwLock};
 
-/// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
-const PEAK_METER_DECAY_MS: f64 = 150.0;
+/// Stereo in and out, the tape and everything per channel is sized by this.
+pub const NUM_CHANNELS: usize = 2;
+
+//the band loops sit on top of the main tape, keep them short so multiband doesn't cost four tapes
+//worth of memory
+const MAX_BAND_LOOP_LENGTH: f32 = 10.0;
 
-const SLIDER_Y_POS: f32 = 60.0;
-const SLIDER_HORIZONTAL_SPACING: f32 = 60.0;
 const BUTTON_WIDTH: f32 = 50.0;
 const BUTTON_HEIGHT: f32 = 25.0;
 
+#[derive(Default, PartialEq, Clone, Copy)]
+enum EditorPage {
+    #[default]
+    Tape,
+    Eq,
+    Effects,
+    Filter,
+    Morph,
+}
+
+/// Anything the editor needs to remember between frames.
+#[derive(Default)]
+struct EditorState {
+    show_pages: bool,
+    page: EditorPage,
+    //the layout the style was last scaled for
+    layout: Option<Layout>,
+    show_analyzer: bool,
+    spectrum: SpectrumAnalyzer,
+    show_shortcuts: bool,
+    show_presets: bool,
+    presets: PresetBrowser,
+    //left and right reel, integrated from the tape's velocity
+    reel_angles: [f32; 2],
+    //set by the build closure from the skin, or the embedded deck if there isn't one
+    textures: Option<UiTextures>,
+    positions: SkinPositions,
+}
+
 struct MisoPaste {
     params: Arc<MisoPasteParams>,
     es: EQSTATE,
+    peq: PARAMETRICSTATE,
     tape: TAPESTATE,
+    //low, mid and high loops for multiband mode
+    band_tapes: [TAPESTATE; 3],
+    grains: GRAINSTATE,
+    slices: SLICESTATE,
+    svf: SVFSTATE,
+    morph: MORPHSTATE,
+    //clear and undo act once per press, however long they're held
+    clear_held: bool,
+    undo_held: bool,
     //GUI stuff
-    peak_meter_decay_weight: f32,
-    peak_meter: Arc<AtomicF32>,
+    meter: METERSTATE,
+    meter_data: Arc<MeterData>,
+    waveform: WAVEFORMSTATE,
+    waveform_data: Arc<WaveformData>,
+    analyzer_data: Arc<AnalyzerData>,
     tape_pos: Arc<AtomicF32>,
+    tape_velocity: Arc<AtomicF32>,
+    tape_transfer: Arc<TapeTransfer>,
+    //the embedded deck, what skins fall back to
     images: UiImages,
 }
 
@@ -32,11 +110,28 @@ impl Default for MisoPaste {
         Self {
             params: Arc::new(MisoPasteParams::default()),
             es: EQSTATE::default(),
+            peq: PARAMETRICSTATE::default(),
             tape: TAPESTATE::default(),
+            band_tapes: [
+                TAPESTATE::default(),
+                TAPESTATE::default(),
+                TAPESTATE::default(),
+            ],
+            grains: GRAINSTATE::default(),
+            slices: SLICESTATE::default(),
+            svf: SVFSTATE::default(),
+            morph: MORPHSTATE::default(),
+            clear_held: false,
+            undo_held: false,
             //GUI
-            peak_meter_decay_weight: 1.0,
-            peak_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            meter: METERSTATE::default(),
+            meter_data: Arc::new(MeterData::default()),
+            waveform: WAVEFORMSTATE::default(),
+            waveform_data: Arc::new(WaveformData::default()),
+            analyzer_data: Arc::new(AnalyzerData::default()),
             tape_pos: Arc::new(AtomicF32::new(0.0)),
+            tape_velocity: Arc::new(AtomicF32::new(0.0)),
+            tape_transfer: Arc::new(TapeTransfer::default()),
             images: UiImages::default(),
         }
     }
@@ -48,39 +143,154 @@ struct MisoPasteParams {
     /// these IDs remain constant, you can rename and reorder these fields as you wish. The
     /// parameters are exposed to the host in the same order they were defined. In this case, this
     /// gain parameter is stored as linear gain while the values are displayed in decibels.
+    ///
+    /// This only sets how much input goes onto the tape, it keeps its old `gain` ID so existing
+    /// sessions still load.
     #[id = "gain"]
-    pub gain: FloatParam,
+    pub input_to_tape: FloatParam,
+    #[id = "dry level"]
+    pub dry_level: FloatParam,
+    #[id = "wet level"]
+    pub wet_level: FloatParam,
+    #[id = "kill dry"]
+    pub kill_dry: BoolParam,
     #[id = "low gain"]
     pub low_gain: FloatParam,
     #[id = "mid gain"]
     pub mid_gain: FloatParam,
     #[id = "high gain"]
     pub high_gain: FloatParam,
+    #[id = "low solo"]
+    pub low_solo: BoolParam,
+    #[id = "mid solo"]
+    pub mid_solo: BoolParam,
+    #[id = "high solo"]
+    pub high_solo: BoolParam,
+    #[id = "low mute"]
+    pub low_mute: BoolParam,
+    #[id = "mid mute"]
+    pub mid_mute: BoolParam,
+    #[id = "high mute"]
+    pub high_mute: BoolParam,
+    #[id = "low crossover"]
+    pub low_freq: FloatParam,
+    #[id = "high crossover"]
+    pub high_freq: FloatParam,
+    #[id = "crossover"]
+    pub crossover: EnumParam<Crossover>,
+    #[id = "eq placement"]
+    pub eq_placement: EnumParam<EqPlacement>,
+    #[nested(array, group = "parametric eq")]
+    pub bands: [BandParams; NUM_BANDS],
+    #[id = "multiband"]
+    pub multiband: BoolParam,
+    #[nested(array, group = "band loops")]
+    pub band_loops: [BandLoopParams; 3],
     #[id = "tape speed"]
     pub tape_speed: FloatParam,
+    #[id = "speed octave"]
+    pub speed_octave: EnumParam<SpeedOctave>,
     #[id = "tape length"]
     pub tape_length: FloatParam,
     #[id = "clear"]
     pub clear: BoolParam,
+    #[id = "undo"]
+    pub undo: BoolParam,
     #[id = "reverse"]
     pub reverse: BoolParam,
     #[id = "fast forward"]
     pub fast_forward: BoolParam,
     #[id = "play / pause"]
     pub play_pause: BoolParam,
-
+    #[id = "granular"]
+    pub granular: BoolParam,
+    #[id = "grain size"]
+    pub grain_size: FloatParam,
+    #[id = "grain density"]
+    pub grain_density: FloatParam,
+    #[id = "grain spray"]
+    pub grain_spray: FloatParam,
+    #[id = "grain pitch"]
+    pub grain_pitch: FloatParam,
+    #[id = "grain reverse"]
+    pub grain_reverse: FloatParam,
+    #[id = "stutter"]
+    pub stutter: BoolParam,
+    #[id = "slice count"]
+    pub slice_count: IntParam,
+    #[id = "slice repeats"]
+    pub slice_repeats: IntParam,
+    #[id = "slice pattern"]
+    pub slice_pattern: EnumParam<SlicePattern>,
+    #[id = "slice mode"]
+    pub slice_mode: EnumParam<SliceMode>,
+    #[id = "slice division"]
+    pub slice_division: EnumParam<SliceDivision>,
+    #[id = "feedback"]
+    pub feedback: FloatParam,
+    #[id = "echo"]
+    pub echo: BoolParam,
+    #[id = "head select"]
+    pub head_select: EnumParam<HeadSelect>,
+    #[nested(array, group = "echo heads")]
+    pub heads: [HeadParams; NUM_HEADS],
+    #[id = "freeze"]
+    pub freeze: BoolParam,
+    #[id = "freeze latch"]
+    pub freeze_latch: BoolParam,
+    #[id = "freeze window"]
+    pub freeze_window: FloatParam,
+    #[id = "filter"]
+    pub filter: BoolParam,
+    #[id = "filter mode"]
+    pub filter_mode: EnumParam<FilterMode>,
+    #[id = "filter cutoff"]
+    pub filter_cutoff: FloatParam,
+    #[id = "filter resonance"]
+    pub filter_resonance: FloatParam,
+    #[id = "lfo rate"]
+    pub lfo_rate: FloatParam,
+    #[id = "lfo sync"]
+    pub lfo_sync: BoolParam,
+    #[id = "lfo division"]
+    pub lfo_division: EnumParam<LfoDivision>,
+    #[id = "lfo depth"]
+    pub lfo_depth: FloatParam,
+    #[id = "env amount"]
+    pub env_amount: FloatParam,
+    #[id = "env attack"]
+    pub env_attack: FloatParam,
+    #[id = "env release"]
+    pub env_release: FloatParam,
+    #[id = "morph on"]
+    pub morph_enabled: BoolParam,
+    #[id = "morph"]
+    pub morph: FloatParam,
+
+    /// The A/B snapshots the morph moves between.
+    #[persist = "snapshots"]
+    snapshots: RwLock<Snapshots>,
+    /// Set by the editor whenever the snapshots change so the audio thread picks them up.
+    snapshots_changed: AtomicBool,
+
+    /// The host's tempo as of the last process call, for showing the tape length in beats.
+    host_tempo: Arc<AtomicF32>,
+
+    /// The window size, set from the scale menu. Takes effect the next time the editor is opened.
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
 }
 
 impl Default for MisoPasteParams {
     fn default() -> Self {
+        let host_tempo = Arc::new(AtomicF32::new(120.0));
+
         Self {
             // This gain is stored as linear gain. NIH-plug comes with useful conversion functions
             // to treat these kinds of parameters as if we were dealing with decibels. Storing this
             // as decibels is easier to work with, but requires a conversion for every sample.
-            gain: FloatParam::new(
-                "Gain",
+            input_to_tape: FloatParam::new(
+                "input to tape",
                 0.01,
                 FloatRange::Linear {
                     min: 0.01,
@@ -89,7 +299,41 @@ impl Default for MisoPasteParams {
             )
             // Because the gain parameter is stored as linear gain instead of storing the value as
             // decibels, we need logarithmic smoothing
-            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            dry_level: FloatParam::new(
+                "dry level",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-60.0),
+                    max: util::db_to_gain(6.0),
+                    factor: FloatRange::gain_skew_factor(-60.0, 6.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            wet_level: FloatParam::new(
+                "wet level",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-60.0),
+                    max: util::db_to_gain(6.0),
+                    factor: FloatRange::gain_skew_factor(-60.0, 6.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            // For running the plugin on a send/aux bus where the dry signal is already in the mix
+            kill_dry: BoolParam::new("kill dry", false),
 
             low_gain: FloatParam::new(
                 "low gain",
@@ -99,7 +343,10 @@ impl Default for MisoPasteParams {
                     max: 2.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
             mid_gain: FloatParam::new(
                 "mid gain",
@@ -109,7 +356,10 @@ impl Default for MisoPasteParams {
                     max: 2.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
             high_gain: FloatParam::new(
                 "high gain",
@@ -119,7 +369,67 @@ impl Default for MisoPasteParams {
                     max: 2.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            low_solo: BoolParam::new("low solo", false),
+            mid_solo: BoolParam::new("mid solo", false),
+            high_solo: BoolParam::new("high solo", false),
+            low_mute: BoolParam::new("low mute", false),
+            mid_mute: BoolParam::new("mid mute", false),
+            high_mute: BoolParam::new("high mute", false),
+
+            low_freq: FloatParam::new(
+                "low crossover",
+                LOWFREQ,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            high_freq: FloatParam::new(
+                "high crossover",
+                HIGHFREQ,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 16000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            crossover: EnumParam::new("crossover", Crossover::OnePole),
+
+            eq_placement: EnumParam::new("eq placement", EqPlacement::Input),
+
+            bands: [
+                BandParams::new(1, BandType::LowShelf, 100.0),
+                BandParams::new(2, BandType::Peak, 500.0),
+                BandParams::new(3, BandType::Peak, 2000.0),
+                BandParams::new(4, BandType::HighShelf, 8000.0),
+            ],
+
+            // Splits the input with the three band EQ and gives each band its own loop. The EQ
+            // placement doesn't apply to the split while this is on, the parametric EQ still runs
+            // on the input for the input placement and on the summed loops otherwise. The sum is
+            // printed over the main tape so the overview and saved tapes show what's playing. The
+            // band loops always play straight, multiband overrides echo, granular and stutter
+            multiband: BoolParam::new("multiband", false),
+
+            band_loops: [
+                BandLoopParams::new("low"),
+                BandLoopParams::new("mid"),
+                BandLoopParams::new("high"),
+            ],
 
             tape_speed: FloatParam::new(
                 "tape speed",
@@ -131,6 +441,8 @@ impl Default for MisoPasteParams {
             )
             .with_smoother(SmoothingStyle::Logarithmic(50.0)),
 
+            speed_octave: EnumParam::new("speed octave", SpeedOctave::Unity),
+
             tape_length: FloatParam::new(
                 "tape length",
                 6.0,
@@ -139,17 +451,402 @@ impl Default for MisoPasteParams {
                     max: 60.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string({
+                let host_tempo = host_tempo.clone();
+                Arc::new(move |value| {
+                    let beats =
+                        value * host_tempo.load(std::sync::atomic::Ordering::Relaxed) / 60.0;
+                    format!("{value:.2} s / {beats:.1} beats")
+                })
+            })
+            .with_string_to_value(Arc::new(|string| {
+                //seconds, the beats are only for show
+                string
+                    .split_whitespace()
+                    .next()
+                    .and_then(|seconds| seconds.trim_end_matches('s').parse().ok())
+            })),
 
             clear: BoolParam::new("clear", false),
 
+            // Brings back what the last clear wiped, or re-clears if it's pressed again
+            undo: BoolParam::new("undo", false),
+
             reverse: BoolParam::new("reverse", false),
 
             fast_forward: BoolParam::new("fast forward", false),
 
             play_pause: BoolParam::new("play / pause", true),
 
-            editor_state: EguiState::from_size(512, 256),
+            granular: BoolParam::new("granular", false),
+
+            grain_size: FloatParam::new(
+                "grain size",
+                100.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            grain_density: FloatParam::new(
+                "grain density",
+                20.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" /s")
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            grain_spray: FloatParam::new(
+                "grain spray",
+                50.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1000.0,
+                },
+            )
+            .with_unit(" ms")
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            grain_pitch: FloatParam::new(
+                "grain pitch",
+                0.0,
+                FloatRange::Linear {
+                    min: -24.0,
+                    max: 24.0,
+                },
+            )
+            .with_unit(" st")
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            grain_reverse: FloatParam::new(
+                "grain reverse",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage())
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            stutter: BoolParam::new("stutter", false),
+
+            slice_count: IntParam::new("slice count", 8, IntRange::Linear { min: 2, max: 32 }),
+
+            slice_repeats: IntParam::new("slice repeats", 2, IntRange::Linear { min: 1, max: 8 }),
+
+            slice_pattern: EnumParam::new("slice pattern", SlicePattern::Repeat),
+
+            slice_mode: EnumParam::new("slice mode", SliceMode::Equal),
+
+            slice_division: EnumParam::new("slice division", SliceDivision::Eighth),
+
+            feedback: FloatParam::new("feedback", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage())
+                .with_smoother(SmoothingStyle::Linear(50.0)),
+
+            echo: BoolParam::new("echo", false),
+
+            head_select: EnumParam::new("head select", HeadSelect::Custom),
+
+            heads: [1, 2, 3, 4].map(HeadParams::new),
+
+            freeze: BoolParam::new("freeze", false),
+
+            freeze_latch: BoolParam::new("freeze latch", true),
+
+            // 0 ms freezes the whole loop, anything else loops just that much for a drone
+            freeze_window: FloatParam::new(
+                "freeze window",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1000.0,
+                },
+            )
+            .with_unit(" ms"),
+
+            filter: BoolParam::new("filter", false),
+
+            filter_mode: EnumParam::new("filter mode", FilterMode::LowPass),
+
+            filter_cutoff: FloatParam::new(
+                "filter cutoff",
+                2000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            filter_resonance: FloatParam::new(
+                "filter resonance",
+                0.2,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            lfo_rate: FloatParam::new(
+                "lfo rate",
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+
+            lfo_sync: BoolParam::new("lfo sync", false),
+
+            lfo_division: EnumParam::new("lfo division", LfoDivision::Bar),
+
+            // Depth and envelope amount are both in octaves of cutoff movement
+            lfo_depth: FloatParam::new("lfo depth", 0.0, FloatRange::Linear { min: 0.0, max: 4.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit(" oct")
+                .with_value_to_string(formatters::v2s_f32_rounded(2)),
+
+            env_amount: FloatParam::new(
+                "env amount",
+                0.0,
+                FloatRange::Linear {
+                    min: -4.0,
+                    max: 4.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" oct")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+
+            env_attack: FloatParam::new(
+                "env attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" ms"),
+
+            env_release: FloatParam::new(
+                "env release",
+                200.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" ms"),
+
+            // Moves every float parameter from snapshot A to snapshot B
+            morph_enabled: BoolParam::new("morph on", false),
+
+            morph: FloatParam::new("morph", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            snapshots: RwLock::new(Snapshots::default()),
+            snapshots_changed: AtomicBool::new(true),
+
+            host_tempo,
+            editor_state: EguiState::from_size(
+                layout::EDITOR_WIDTH as u32,
+                layout::EDITOR_HEIGHT as u32,
+            ),
+        }
+    }
+}
+
+impl MisoPasteParams {
+    fn editor_scale(&self) -> f32 {
+        self.editor_state.size().0 as f32 / layout::EDITOR_WIDTH
+    }
+
+    /// Size the window for `scale`. nih_plug_egui only reads the size as the window opens, and the
+    /// persisted state has no setter of its own, so it goes in the same way a saved state would.
+    fn set_editor_scale(&self, scale: f32) {
+        let (width, height) = layout::window_size(scale);
+        if let Ok(state) = serde_json::to_string(&*EguiState::from_size(width, height)) {
+            self.deserialize_fields(&BTreeMap::from([(String::from("editor-state"), state)]));
+        }
+    }
+}
+
+/// One band of the parametric EQ. Bands start switched off so the EQ is transparent until it's
+/// needed.
+#[derive(Params)]
+struct BandParams {
+    #[id = "band on"]
+    pub enabled: BoolParam,
+    #[id = "band type"]
+    pub band_type: EnumParam<BandType>,
+    #[id = "band freq"]
+    pub frequency: FloatParam,
+    #[id = "band gain"]
+    pub gain: FloatParam,
+    #[id = "band q"]
+    pub q: FloatParam,
+}
+
+impl BandParams {
+    fn new(band: usize, band_type: BandType, frequency: f32) -> Self {
+        Self {
+            enabled: BoolParam::new(format!("band {band} on"), false),
+
+            band_type: EnumParam::new(format!("band {band} type"), band_type),
+
+            frequency: FloatParam::new(
+                format!("band {band} freq"),
+                frequency,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            gain: FloatParam::new(
+                format!("band {band} gain"),
+                0.0,
+                FloatRange::Linear {
+                    min: -18.0,
+                    max: 18.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            q: FloatParam::new(
+                format!("band {band} q"),
+                0.707,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 18.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+        }
+    }
+}
+
+/// The loop for one EQ band in multiband mode.
+#[derive(Params)]
+struct BandLoopParams {
+    #[id = "loop length"]
+    pub length: FloatParam,
+    #[id = "loop speed"]
+    pub speed: FloatParam,
+    #[id = "loop feedback"]
+    pub feedback: FloatParam,
+}
+
+impl BandLoopParams {
+    fn new(band: &str) -> Self {
+        Self {
+            length: FloatParam::new(
+                format!("{band} loop length"),
+                6.0,
+                FloatRange::Linear {
+                    min: 2.0,
+                    max: MAX_BAND_LOOP_LENGTH,
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" s"),
+
+            speed: FloatParam::new(
+                format!("{band} loop speed"),
+                1.0,
+                FloatRange::Linear {
+                    min: 0.10,
+                    max: 3.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0)),
+
+            feedback: FloatParam::new(
+                format!("{band} loop feedback"),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage())
+            .with_smoother(SmoothingStyle::Linear(50.0)),
+        }
+    }
+}
+
+/// One of the echo mode's playback heads. The heads default to evenly spaced delays with only the
+/// first one switched on.
+#[derive(Params)]
+struct HeadParams {
+    #[id = "head on"]
+    pub enabled: BoolParam,
+    #[id = "head level"]
+    pub level: FloatParam,
+    #[id = "head delay"]
+    pub delay: FloatParam,
+}
+
+impl HeadParams {
+    fn new(head: usize) -> Self {
+        Self {
+            enabled: BoolParam::new(format!("head {head} on"), head == 1),
+
+            level: FloatParam::new(
+                format!("head {head} level"),
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            delay: FloatParam::new(
+                format!("head {head} delay"),
+                head as f32 * 0.15,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 2.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" s")
+            .with_value_to_string(formatters::v2s_f32_rounded(3))
+            .with_smoother(SmoothingStyle::Linear(50.0)),
         }
     }
 }
@@ -165,8 +862,8 @@ impl Plugin for MisoPaste {
     // The first audio IO layout is used as the default. The other layouts may be selected either
     // explicitly or automatically by the host or the user depending on the plugin API/backend.
     const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
+        main_input_channels: NonZeroU32::new(NUM_CHANNELS as u32),
+        main_output_channels: NonZeroU32::new(NUM_CHANNELS as u32),
 
         aux_input_ports: &[],
         aux_output_ports: &[],
@@ -196,132 +893,367 @@ impl Plugin for MisoPaste {
     }
 
     fn editor(&self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        fn rect_from_point(x: f32, y: f32) -> egui::Rect {
-            egui::Rect::from_two_pos(egui::pos2(x, y), egui::pos2(x + 1.0, y + 1.0))
+        fn rect_from_point(layout: &Layout, [x, y]: [f32; 2]) -> egui::Rect {
+            egui::Rect::from_min_size(layout.pos(x, y), layout.size(1.0, 1.0))
         }
-        fn button_rect(xpos: f32, ypos: f32) -> egui::Rect {
-            egui::Rect::from_center_size(
-                egui::pos2(xpos, ypos),
-                egui::vec2(BUTTON_WIDTH, BUTTON_HEIGHT),
-            )
+        fn button_rect(layout: &Layout, [xpos, ypos]: [f32; 2]) -> egui::Rect {
+            layout.rect_from_center_size(xpos, ypos, BUTTON_WIDTH, BUTTON_HEIGHT)
+        }
+        fn skin_rect(layout: &Layout, [min_x, min_y, max_x, max_y]: [f32; 4]) -> egui::Rect {
+            layout.rect(min_x, min_y, max_x, max_y)
+        }
+        fn reel_rect(layout: &Layout, [x, y]: [f32; 2], size: f32) -> egui::Rect {
+            layout.rect_from_center_size(x, y, size, size)
+        }
+        /// A slider over the parameter's normalized range, so it can't ask for values the
+        /// parameter can't hold and shows them with the parameter's own formatting. egui 0.19 has
+        /// no hook for parsing typed text, so a number typed into the value box is taken as a
+        /// normalized 0 to 1 value, anything else is ignored.
+        fn param_slider<'a, P: Param>(setter: &'a ParamSetter, param: &'a P) -> egui::Slider<'a> {
+            egui::widgets::Slider::from_get_set(0.0..=1.0, move |new_value| match new_value {
+                Some(value) => {
+                    setter.begin_set_parameter(param);
+                    setter.set_parameter_normalized(param, value as f32);
+                    setter.end_set_parameter(param);
+
+                    value
+                }
+                None => param.unmodulated_normalized_value() as f64,
+            })
+            .custom_formatter(move |value, _| param.normalized_value_to_string(value as f32, true))
+        }
+
+        fn knob<P: Param>(ui: &mut egui::Ui, setter: &ParamSetter, param: &P, label: &str) {
+            //follows the layout's scaled metrics
+            let diameter = ui.spacing().interact_size.y * 2.0;
+            widgets::labelled_knob(ui, setter, param, label, diameter);
+        }
+
+        fn enum_combo<T: Enum + PartialEq + 'static>(
+            ui: &mut egui::Ui,
+            setter: &ParamSetter,
+            param: &EnumParam<T>,
+            label: &str,
+        ) {
+            let current = param.value().to_index();
+
+            egui::ComboBox::from_label(label)
+                .selected_text(T::variants()[current])
+                .show_ui(ui, |ui| {
+                    for (index, name) in T::variants().iter().enumerate() {
+                        if ui.selectable_label(index == current, *name).clicked() {
+                            setter.begin_set_parameter(param);
+                            setter.set_parameter(param, T::from_index(index));
+                            setter.end_set_parameter(param);
+                        }
+                    }
+                });
+        }
+
+        fn bool_toggle(ui: &mut egui::Ui, setter: &ParamSetter, param: &BoolParam, label: &str) {
+            if ui.selectable_label(param.value(), label).clicked() {
+                setter.begin_set_parameter(param);
+                setter.set_parameter(param, !param.value());
+                setter.end_set_parameter(param);
+            }
+        }
+
+        fn tape_page(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.input_to_tape, "input");
+                knob(ui, setter, &params.tape_length, "length");
+                knob(ui, setter, &params.tape_speed, "speed");
+                knob(ui, setter, &params.feedback, "feedback");
+            });
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.dry_level, "dry");
+                knob(ui, setter, &params.wet_level, "wet");
+                knob(ui, setter, &params.freeze_window, "frz window");
+            });
+            ui.horizontal(|ui| {
+                enum_combo(ui, setter, &params.speed_octave, "octave");
+                bool_toggle(ui, setter, &params.kill_dry, "kill dry");
+                bool_toggle(ui, setter, &params.freeze_latch, "freeze latch");
+            });
+        }
+
+        fn eq_page(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            ui.horizontal(|ui| {
+                let bands = [
+                    ("low", &params.low_gain, &params.low_solo, &params.low_mute),
+                    ("mid", &params.mid_gain, &params.mid_solo, &params.mid_mute),
+                    (
+                        "high",
+                        &params.high_gain,
+                        &params.high_solo,
+                        &params.high_mute,
+                    ),
+                ];
+                for (name, gain, solo, mute) in bands {
+                    ui.vertical(|ui| {
+                        knob(ui, setter, gain, name);
+                        ui.horizontal(|ui| {
+                            bool_toggle(ui, setter, solo, "S");
+                            bool_toggle(ui, setter, mute, "M");
+                        });
+                    });
+                }
+                knob(ui, setter, &params.low_freq, "low x");
+                knob(ui, setter, &params.high_freq, "high x");
+            });
+            enum_combo(ui, setter, &params.crossover, "crossover");
+            enum_combo(ui, setter, &params.eq_placement, "placement");
+
+            ui.separator();
+            bool_toggle(ui, setter, &params.multiband, "multiband");
+            for (name, band_loop) in ["low", "mid", "high"].iter().zip(params.band_loops.iter()) {
+                ui.horizontal(|ui| {
+                    ui.label(*name);
+                    knob(ui, setter, &band_loop.length, "length");
+                    knob(ui, setter, &band_loop.speed, "speed");
+                    knob(ui, setter, &band_loop.feedback, "feedback");
+                });
+            }
+
+            ui.separator();
+            for (i, band) in params.bands.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    bool_toggle(ui, setter, &band.enabled, &format!("band {}", i + 1));
+                    knob(ui, setter, &band.frequency, "freq");
+                    knob(ui, setter, &band.gain, "gain");
+                    knob(ui, setter, &band.q, "q");
+                    enum_combo(ui, setter, &band.band_type, &format!("type {}", i + 1));
+                });
+            }
+        }
+
+        fn effects_page(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            bool_toggle(ui, setter, &params.granular, "granular");
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.grain_size, "size");
+                knob(ui, setter, &params.grain_density, "density");
+                knob(ui, setter, &params.grain_spray, "spray");
+                knob(ui, setter, &params.grain_pitch, "pitch");
+                knob(ui, setter, &params.grain_reverse, "reverse");
+            });
+
+            ui.separator();
+            bool_toggle(ui, setter, &params.stutter, "stutter");
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.slice_count, "slices");
+                knob(ui, setter, &params.slice_repeats, "repeats");
+                ui.vertical(|ui| {
+                    enum_combo(ui, setter, &params.slice_pattern, "pattern");
+                    enum_combo(ui, setter, &params.slice_mode, "mode");
+                    enum_combo(ui, setter, &params.slice_division, "division");
+                });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                bool_toggle(ui, setter, &params.echo, "echo");
+                enum_combo(ui, setter, &params.head_select, "heads");
+            });
+            ui.horizontal(|ui| {
+                for (i, head) in params.heads.iter().enumerate() {
+                    ui.vertical(|ui| {
+                        bool_toggle(ui, setter, &head.enabled, &format!("head {}", i + 1));
+                        knob(ui, setter, &head.level, "level");
+                        knob(ui, setter, &head.delay, "delay");
+                    });
+                }
+            });
+        }
+
+        fn compare_controls(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            let mut snapshots = params.snapshots.write().unwrap();
+            let mut changed = false;
+
+            for (slot, name) in ["A", "B"].into_iter().enumerate() {
+                if ui
+                    .selectable_label(snapshots.active == slot, name)
+                    .clicked()
+                {
+                    snapshots.select(slot, params, setter);
+                    changed = true;
+                }
+            }
+            if ui
+                .small_button("copy")
+                .on_hover_text("copy these settings to the other slot")
+                .clicked()
+            {
+                snapshots.copy_active(params);
+                changed = true;
+            }
+
+            //switching the morph on keeps what's dialled in now as part of it
+            if ui
+                .selectable_label(params.morph_enabled.value(), "morph")
+                .clicked()
+            {
+                if !params.morph_enabled.value() {
+                    snapshots.store_active(params);
+                    changed = true;
+                }
+
+                setter.begin_set_parameter(&params.morph_enabled);
+                setter.set_parameter(&params.morph_enabled, !params.morph_enabled.value());
+                setter.end_set_parameter(&params.morph_enabled);
+            }
+
+            if changed {
+                params
+                    .snapshots_changed
+                    .store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        fn morph_page(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            ui.horizontal(|ui| compare_controls(ui, setter, params));
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.morph, "A to B");
+                ui.label(
+                    "with the morph on, every continuous control moves between\nthe A and B \
+                     settings, the switches stay where they are",
+                );
+            });
+        }
+
+        fn filter_page(ui: &mut egui::Ui, setter: &ParamSetter, params: &MisoPasteParams) {
+            ui.horizontal(|ui| {
+                bool_toggle(ui, setter, &params.filter, "filter");
+                enum_combo(ui, setter, &params.filter_mode, "mode");
+            });
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.filter_cutoff, "cutoff");
+                knob(ui, setter, &params.filter_resonance, "resonance");
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                bool_toggle(ui, setter, &params.lfo_sync, "lfo sync");
+                enum_combo(ui, setter, &params.lfo_division, "division");
+            });
+            ui.horizontal(|ui| {
+                knob(ui, setter, &params.lfo_rate, "lfo rate");
+                knob(ui, setter, &params.lfo_depth, "lfo depth");
+                knob(ui, setter, &params.env_amount, "env amount");
+                knob(ui, setter, &params.env_attack, "attack");
+                knob(ui, setter, &params.env_release, "release");
+            });
         }
 
         let params = self.params.clone();
-        let peak_meter = self.peak_meter.clone();
+        let meter_data = self.meter_data.clone();
+        let waveform_data = self.waveform_data.clone();
+        let analyzer_data = self.analyzer_data.clone();
         let images = self.images.clone();
         let tape_pos = self.tape_pos.clone();
+        let tape_velocity = self.tape_velocity.clone();
+        let tape_transfer = self.tape_transfer.clone();
 
-        //NOTE - Window size defined in the default: editor_state
+        //the window opens at the persisted size, there's no resizing it once it's open
         create_egui_editor(
             self.params.editor_state.clone(),
-            (),
-            |_, _| {},
-            move |egui_ctx, setter, _state| {
+            EditorState::default(),
+            //runs every time the window opens, so a new skin only needs the editor reopening
+            move |egui_ctx, state| {
+                let skin = Skin::load(&images);
+                state.textures = Some(UiTextures::load(egui_ctx, &skin.images));
+                state.positions = skin.positions;
+                state.presets = PresetBrowser::load();
+            },
+            move |egui_ctx, setter, state| {
+                let Some(textures) = &state.textures else {
+                    return;
+                };
+                let positions = state.positions;
+
+                //lay everything out against the window we actually got
+                let layout = Layout::fit(egui_ctx.input().screen_rect());
+                if state.layout != Some(layout) {
+                    layout.apply_style(egui_ctx);
+                    state.layout = Some(layout);
+                }
+
+                let shortcuts = Shortcuts::read(egui_ctx);
+                if shortcuts.toggle_help {
+                    state.show_shortcuts = !state.show_shortcuts;
+                }
+
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     // NOTE: See `plugins/diopser/src/editor.rs` for an example using the generic UI widget
 
-                    ui.spacing_mut().slider_width = 130.0;
-
                     //IMAGES
                     //background
-                    let background_texture = ui.ctx().load_texture(
-                        "background",
-                        images.background.to_owned(),
-                        egui::TextureFilter::Linear,
+                    let background_image = egui::Image::new(
+                        &textures.background,
+                        layout.size(layout::EDITOR_WIDTH, layout::EDITOR_HEIGHT),
                     );
 
-                    let background_image =
-                        egui::Image::new(&background_texture, egui::vec2(512.0, 256.0));
+                    ui.put(layout.full_rect(), background_image);
 
-                    ui.put(
-                        egui::Rect::from_points(&[egui::pos2(0.0, 0.0), egui::pos2(512.0, 256.0)]),
-                        background_image,
-                    );
-
-                    //reel to reel
-                    let reel_l_texture = ui.ctx().load_texture(
-                        "reel_l",
-                        images.reel_l.to_owned(),
-                        egui::TextureFilter::Linear,
-                    );
-
-                    let reel_l_image = egui::Image::new(&reel_l_texture, egui::vec2(172.0, 172.0))
-                        .rotate(
-                            tape_pos.load(std::sync::atomic::Ordering::Relaxed)
-                                * 360.0_f32.to_radians(),
-                            egui::vec2(0.5, 0.5),
-                        );
-
-                    let reel_r_texture = ui.ctx().load_texture(
-                        "reel_r",
-                        images.reel_r.to_owned(),
-                        egui::TextureFilter::Linear,
-                    );
+                    //right click on the background for the editor scale
+                    ui.interact(
+                        layout.full_rect(),
+                        egui::Id::new("background"),
+                        egui::Sense::click(),
+                    )
+                    .context_menu(|ui| {
+                        ui.label("editor scale, applies on reopen");
+                        let current = params.editor_scale();
+                        for scale in layout::SCALE_STEPS {
+                            let label = format!("{:.0}%", scale * 100.0);
+                            let selected = (current - scale).abs() < 0.01;
+                            if ui.selectable_label(selected, label).clicked() {
+                                params.set_editor_scale(scale);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    //reel to reel, the left reel pays out and the right takes up
+                    let position = tape_pos.load(std::sync::atomic::Ordering::Relaxed);
+                    let velocity = tape_velocity.load(std::sync::atomic::Ordering::Relaxed);
+                    let dt = ui.input().stable_dt.min(0.1);
+                    let packs = [1.0 - position, position];
+                    if velocity != 0.0 {
+                        ui.ctx().request_repaint();
+                    }
 
-                    let reel_r_image = egui::Image::new(&reel_r_texture, egui::vec2(172.0, 172.0))
-                        .rotate(
-                            tape_pos.load(std::sync::atomic::Ordering::Relaxed)
-                                * 360.0_f32.to_radians(),
-                            egui::vec2(0.5, 0.5),
-                        );
-                    //TODO - build in offsets so we can move the two reels around more easily
+                    for (angle, pack) in state.reel_angles.iter_mut().zip(packs) {
+                        *angle = (*angle + widgets::reel_angular_velocity(velocity, pack) * dt)
+                            % std::f32::consts::TAU;
+                    }
 
-                    ui.put(
-                        egui::Rect::from_center_size(
-                            egui::pos2(246.0, 92.0),
-                            egui::vec2(172.0, 172.0),
-                        ),
-                        reel_l_image,
+                    widgets::reel(
+                        ui,
+                        reel_rect(&layout, positions.reel_l, positions.reel_size),
+                        &textures.reel_l,
+                        state.reel_angles[0],
+                        packs[0],
                     );
-                    ui.put(
-                        egui::Rect::from_center_size(
-                            egui::pos2(420.0, 92.0),
-                            egui::vec2(172.0, 172.0),
-                        ),
-                        reel_r_image,
+                    widgets::reel(
+                        ui,
+                        reel_rect(&layout, positions.reel_r, positions.reel_size),
+                        &textures.reel_r,
+                        state.reel_angles[1],
+                        packs[1],
                     );
 
                     //SLIDERS
-                    //gain
-                    let gain_slider =
-                        egui::widgets::Slider::from_get_set(
-                            0.0..=1.0,
-                            |new_value| match new_value {
-                                Some(value) => {
-                                    let new_value = value as f32;
-
-                                    setter.begin_set_parameter(&params.gain);
-                                    setter.set_parameter(&params.gain, new_value);
-                                    setter.end_set_parameter(&params.gain);
-
-                                    value
-                                }
-                                None => params.gain.value() as f64,
-                            },
-                        )
-                        .vertical();
-                    ui.put(rect_from_point(64.0, SLIDER_Y_POS), gain_slider);
+                    //input to tape
+                    let gain_slider = param_slider(setter, &params.input_to_tape).vertical();
+                    ui.put(
+                        rect_from_point(&layout, positions.input_slider),
+                        gain_slider,
+                    );
 
                     //tape length
-                    let tape_length_slider = egui::widgets::Slider::from_get_set(
-                        0.25..=60.0,
-                        |new_value| match new_value {
-                            Some(value) => {
-                                let new_value = value as f32;
-
-                                setter.begin_set_parameter(&params.tape_length);
-                                setter.set_parameter(&params.tape_length, new_value);
-                                setter.end_set_parameter(&params.tape_length);
-
-                                value
-                            }
-                            None => params.tape_length.value() as f64,
-                        },
-                    )
-                    .vertical();
-
+                    let tape_length_slider = param_slider(setter, &params.tape_length).vertical();
                     ui.put(
-                        rect_from_point(64.0 + SLIDER_HORIZONTAL_SPACING, SLIDER_Y_POS),
+                        rect_from_point(&layout, positions.length_slider),
                         tape_length_slider,
                     );
 
@@ -331,7 +1263,11 @@ impl Plugin for MisoPaste {
 
                     setter.begin_set_parameter(&params.clear);
 
-                    if ui.put(button_rect(440.0, 212.0), panic_button).clicked() {
+                    if ui
+                        .put(button_rect(&layout, positions.clear), panic_button)
+                        .clicked()
+                        || shortcuts.clear
+                    {
                         setter.set_parameter(&params.clear, true);
                     } else {
                         setter.set_parameter(&params.clear, false);
@@ -339,12 +1275,27 @@ impl Plugin for MisoPaste {
 
                     setter.end_set_parameter(&params.clear);
 
+                    //undo only has a shortcut
+                    setter.begin_set_parameter(&params.undo);
+                    setter.set_parameter(&params.undo, shortcuts.undo);
+                    setter.end_set_parameter(&params.undo);
+
+                    if let Some(length) = shortcuts.tape_length {
+                        setter.begin_set_parameter(&params.tape_length);
+                        setter.set_parameter(&params.tape_length, length);
+                        setter.end_set_parameter(&params.tape_length);
+                    }
+
                     //fast forward
                     let ff_button = egui::Button::new("FF").sense(egui::Sense::click_and_drag());
 
                     setter.begin_set_parameter(&params.fast_forward);
 
-                    if ui.put(button_rect(300.0, 212.0), ff_button).dragged() {
+                    if ui
+                        .put(button_rect(&layout, positions.fast_forward), ff_button)
+                        .dragged()
+                        || shortcuts.fast_forward
+                    {
                         setter.set_parameter(&params.fast_forward, true);
                     } else {
                         setter.set_parameter(&params.fast_forward, false);
@@ -358,8 +1309,9 @@ impl Plugin for MisoPaste {
                     setter.begin_set_parameter(&params.play_pause);
 
                     if ui
-                        .put(button_rect(230.0, 212.0), play_pause_button)
+                        .put(button_rect(&layout, positions.play), play_pause_button)
                         .clicked()
+                        || shortcuts.play_pause
                     {
                         setter.set_parameter(&params.play_pause, !&params.play_pause.value());
                     }
@@ -372,7 +1324,11 @@ impl Plugin for MisoPaste {
 
                     setter.begin_set_parameter(&params.reverse);
 
-                    if ui.put(button_rect(370.0, 212.0), reverse_button).dragged() {
+                    if ui
+                        .put(button_rect(&layout, positions.reverse), reverse_button)
+                        .dragged()
+                        || shortcuts.reverse
+                    {
                         setter.set_parameter(&params.reverse, true);
                     } else {
                         setter.set_parameter(&params.reverse, false);
@@ -380,29 +1336,243 @@ impl Plugin for MisoPaste {
 
                     setter.end_set_parameter(&params.reverse);
 
-                    //PEAK METER
-                    // TODO: Add a proper custom widget instead of reusing a progress bar
-                    let peak_meter =
-                        util::gain_to_db(peak_meter.load(std::sync::atomic::Ordering::Relaxed));
-                    let peak_meter_text = if peak_meter > util::MINUS_INFINITY_DB {
-                        format!("{peak_meter:.1} dBFS")
+                    //freeze, either latches on click or holds while pressed
+                    let freeze_button =
+                        egui::Button::new("FRZ").sense(egui::Sense::click_and_drag());
+                    let freeze_response =
+                        ui.put(button_rect(&layout, positions.freeze), freeze_button);
+
+                    setter.begin_set_parameter(&params.freeze);
+
+                    if params.freeze_latch.value() {
+                        if freeze_response.clicked() {
+                            setter.set_parameter(&params.freeze, !params.freeze.value());
+                        }
                     } else {
-                        String::from("-inf dBFS")
-                    };
+                        setter.set_parameter(&params.freeze, freeze_response.dragged());
+                    }
 
-                    let peak_meter_normalized = (peak_meter + 60.0) / 60.0;
-                    ui.allocate_space(egui::Vec2::splat(2.0));
+                    setter.end_set_parameter(&params.freeze);
 
-                    let peak_meter_widget = egui::widgets::ProgressBar::new(peak_meter_normalized)
-                        .text(peak_meter_text);
+                    //A/B
+                    let [compare_x, compare_y] = positions.compare;
+                    ui.allocate_ui_at_rect(
+                        layout.rect_from_center_size(compare_x, compare_y, 150.0, 20.0),
+                        |ui| ui.horizontal(|ui| compare_controls(ui, setter, &params)),
+                    );
 
-                    ui.put(
-                        egui::Rect {
-                            min: egui::pos2(169.0, 235.0),
-                            max: egui::pos2(496.0, 245.0),
+                    //PRESETS
+                    let preset_name = match state.presets.current {
+                        Some(index) => state.presets.entries[index].preset.name.clone(),
+                        None => "no preset".to_owned(),
+                    };
+                    let [preset_x, preset_y] = positions.presets;
+                    ui.allocate_ui_at_rect(
+                        layout.rect_from_center_size(preset_x, preset_y, 150.0, 20.0),
+                        |ui| {
+                            ui.horizontal(|ui| {
+                                let mut step = None;
+                                if ui.small_button("<").clicked() {
+                                    step = state.presets.step(false);
+                                }
+                                if ui.button(preset_name).clicked() {
+                                    state.show_presets = !state.show_presets;
+                                }
+                                if ui.small_button(">").clicked() {
+                                    step = state.presets.step(true);
+                                }
+
+                                if let Some(index) = step {
+                                    state.presets.load_entry(
+                                        index,
+                                        params.as_ref(),
+                                        setter,
+                                        &tape_transfer,
+                                    );
+                                }
+                            });
                         },
-                        peak_meter_widget,
                     );
+
+                    let presets = &mut state.presets;
+                    egui::Window::new("presets")
+                        .open(&mut state.show_presets)
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ui.ctx(), |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                if ui
+                                    .selectable_label(presets.category.is_none(), "all")
+                                    .clicked()
+                                {
+                                    presets.category = None;
+                                }
+                                for category in presets.categories() {
+                                    let selected =
+                                        presets.category.as_deref() == Some(category.as_str());
+                                    if ui.selectable_label(selected, &category).clicked() {
+                                        presets.category = Some(category);
+                                    }
+                                }
+                            });
+                            ui.separator();
+
+                            egui::ScrollArea::vertical()
+                                .max_height(layout.size(0.0, 100.0).y)
+                                .show(ui, |ui| {
+                                    for index in presets.visible() {
+                                        let entry = &presets.entries[index];
+                                        let label = match entry.path {
+                                            Some(_) => entry.preset.name.clone(),
+                                            None => format!("{} (factory)", entry.preset.name),
+                                        };
+
+                                        if ui
+                                            .selectable_label(presets.current == Some(index), label)
+                                            .clicked()
+                                        {
+                                            presets.load_entry(
+                                                index,
+                                                params.as_ref(),
+                                                setter,
+                                                &tape_transfer,
+                                            );
+                                        }
+                                    }
+                                });
+                            ui.separator();
+
+                            egui::Grid::new("preset save").show(ui, |ui| {
+                                ui.label("name");
+                                ui.text_edit_singleline(&mut presets.save_name);
+                                ui.end_row();
+                                ui.label("category");
+                                ui.text_edit_singleline(&mut presets.save_category);
+                                ui.end_row();
+                            });
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut presets.include_tape, "include tape");
+                                if ui.button("save").clicked() {
+                                    presets.start_save(params.as_ref(), &tape_transfer);
+                                }
+                                if ui.button("refresh").clicked() {
+                                    presets.reload();
+                                }
+                            });
+                            if !presets.status.is_empty() {
+                                ui.label(&presets.status);
+                            }
+                        });
+                    presets.finish_pending_save(&tape_transfer);
+
+                    egui::Window::new("shortcuts")
+                        .open(&mut state.show_shortcuts)
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ui.ctx(), |ui| {
+                            egui::Grid::new("shortcut list").show(ui, |ui| {
+                                for (key, action) in shortcuts::SHORTCUT_HELP {
+                                    ui.strong(*key);
+                                    ui.label(*action);
+                                    ui.end_row();
+                                }
+                            });
+                        });
+
+                    //every parameter, a page at a time
+                    if ui
+                        .put(
+                            button_rect(&layout, positions.edit),
+                            egui::Button::new("EDIT"),
+                        )
+                        .clicked()
+                    {
+                        state.show_pages = !state.show_pages;
+                    }
+
+                    let page = &mut state.page;
+                    egui::Window::new("edit")
+                        .open(&mut state.show_pages)
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ui.ctx(), |ui| {
+                            ui.horizontal(|ui| {
+                                for (name, this_page) in [
+                                    ("tape", EditorPage::Tape),
+                                    ("eq", EditorPage::Eq),
+                                    ("effects", EditorPage::Effects),
+                                    ("filter", EditorPage::Filter),
+                                    ("morph", EditorPage::Morph),
+                                ] {
+                                    ui.selectable_value(page, this_page, name);
+                                }
+                            });
+                            ui.separator();
+
+                            egui::ScrollArea::vertical()
+                                .max_height(layout.size(0.0, 180.0).y)
+                                .show(ui, |ui| match page {
+                                    EditorPage::Tape => tape_page(ui, setter, &params),
+                                    EditorPage::Eq => eq_page(ui, setter, &params),
+                                    EditorPage::Effects => effects_page(ui, setter, &params),
+                                    EditorPage::Morph => morph_page(ui, setter, &params),
+                                    EditorPage::Filter => filter_page(ui, setter, &params),
+                                });
+                        });
+
+                    //SPECTRUM
+                    if ui
+                        .put(
+                            button_rect(&layout, positions.spectrum),
+                            egui::Button::new("FFT"),
+                        )
+                        .clicked()
+                    {
+                        state.show_analyzer = !state.show_analyzer;
+                    }
+
+                    if state.show_analyzer {
+                        state.spectrum.update(&analyzer_data);
+                        //keep the spectrum moving even when nothing else is happening
+                        ui.ctx().request_repaint();
+                    }
+
+                    let spectrum = &state.spectrum;
+                    egui::Window::new("spectrum")
+                        .open(&mut state.show_analyzer)
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ui.ctx(), |ui| {
+                            let (rect, _) = ui.allocate_exact_size(
+                                layout.size(400.0, 160.0),
+                                egui::Sense::hover(),
+                            );
+                            widgets::spectrum(
+                                ui,
+                                rect,
+                                spectrum,
+                                analyzer_data
+                                    .sample_rate
+                                    .load(std::sync::atomic::Ordering::Relaxed),
+                                (params.low_freq.value(), params.high_freq.value()),
+                                [
+                                    params.low_gain.value(),
+                                    params.mid_gain.value(),
+                                    params.high_gain.value(),
+                                ],
+                            );
+                        });
+
+                    //WAVEFORM
+                    widgets::waveform(
+                        ui,
+                        skin_rect(&layout, positions.waveform),
+                        &waveform_data,
+                        tape_pos.load(std::sync::atomic::Ordering::Relaxed),
+                    );
+
+                    //METER
+                    widgets::level_meter(ui, skin_rect(&layout, positions.meter), &meter_data);
                 });
             },
         )
@@ -416,14 +1586,25 @@ impl Plugin for MisoPaste {
     ) -> bool {
         //init EQ STATE
         self.es.init(buffer_config.sample_rate);
+        self.peq.init(buffer_config.sample_rate);
         //init TAPESTATE
         self.tape.init(buffer_config.sample_rate);
-
-        // After `PEAK_METER_DECAY_MS` milliseconds of pure silence, the peak meter's value should
-        // have dropped by 12 dB
-        self.peak_meter_decay_weight = 0.25f64
-            .powf((buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
-            as f32;
+        for tape in self.band_tapes.iter_mut() {
+            tape.init_with_max_length(buffer_config.sample_rate, MAX_BAND_LOOP_LENGTH);
+        }
+        self.grains.init(buffer_config.sample_rate);
+        self.slices.init();
+        self.svf.init(buffer_config.sample_rate);
+
+        self.meter.init(buffer_config.sample_rate);
+        self.tape_transfer
+            .reserve(self.tape.capacity(), buffer_config.sample_rate);
+        self.morph.init(self.params.as_ref());
+        self.morph.refresh(&self.params.snapshots.read().unwrap());
+        self.analyzer_data.sample_rate.store(
+            buffer_config.sample_rate,
+            std::sync::atomic::Ordering::Relaxed,
+        );
 
         nih_dbg!(std::env::current_dir().unwrap());
         true
@@ -438,81 +1619,325 @@ impl Plugin for MisoPaste {
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        if self.params.clear.value() {
+        //preset tape saves and loads from the editor
+        self.tape_transfer.service(&mut self.tape);
+
+        let clear = self.params.clear.value();
+        if clear && !self.clear_held {
             self.tape.clear();
+            self.waveform_data.clear();
+            for tape in self.band_tapes.iter_mut() {
+                tape.clear();
+            }
+        }
+        self.clear_held = clear;
+
+        //the overview fills back in as the head passes over the restored tape
+        let undo = self.params.undo.value();
+        if undo && !self.undo_held {
+            self.tape.undo();
+            self.waveform_data.clear();
+            for tape in self.band_tapes.iter_mut() {
+                tape.undo();
+            }
+        }
+        self.undo_held = undo;
+
+        //slices follow the host's beat grid, fall back to 120 bpm if the host doesn't tell us
+        let transport = context.transport();
+        let tempo = transport.tempo.unwrap_or(120.0);
+        self.params
+            .host_tempo
+            .store(tempo as f32, std::sync::atomic::Ordering::Relaxed);
+        let division = self.params.slice_division.value();
+
+        self.slices.count = self.params.slice_count.value() as usize;
+        self.slices.repeats = self.params.slice_repeats.value() as usize;
+        self.slices.pattern = self.params.slice_pattern.value();
+        self.slices.mode = self.params.slice_mode.value();
+        self.slices
+            .set_step_length(self.tape.samplerate(), tempo, division);
+        if let (true, Some(pos_beats)) = (transport.playing, transport.pos_beats()) {
+            self.slices.sync(&self.tape, pos_beats / division.beats());
         }
 
+        //filter lfo, either free running or locked to the host's bars
+        let lfo_division = self.params.lfo_division.value();
+        if self.params.lfo_sync.value() {
+            if let (true, Some(pos_beats)) = (transport.playing, transport.pos_beats()) {
+                self.svf.sync_lfo(pos_beats, lfo_division);
+            }
+        }
+        self.svf.mode = self.params.filter_mode.value();
+
         if self.params.reverse.value() {
             nih_dbg!(self.params.reverse.value());
         }
 
+        //the snapshots only change from the editor, if it's mid write they'll be picked up next block
+        if self
+            .params
+            .snapshots_changed
+            .swap(false, std::sync::atomic::Ordering::Relaxed)
+        {
+            match self.params.snapshots.try_read() {
+                Ok(snapshots) => self.morph.refresh(&snapshots),
+                Err(_) => self
+                    .params
+                    .snapshots_changed
+                    .store(true, std::sync::atomic::Ordering::Relaxed),
+            }
+        }
+        self.morph.process(
+            self.params
+                .morph_enabled
+                .value()
+                .then(|| self.params.morph.value()),
+            self.tape.samplerate(),
+        );
+
+        //through the smoother, which is where the morph sets it
+        let freeze_window = self.params.freeze_window.smoothed.next() / 1000.0;
+        self.tape
+            .set_freeze(self.params.freeze.value(), freeze_window);
+        for tape in self.band_tapes.iter_mut() {
+            tape.set_freeze(self.params.freeze.value(), freeze_window);
+        }
+
+        let editor_open = self.params.editor_state.is_open();
+        let num_channels = buffer.channels() as f32;
+
         for channel_samples in buffer.iter_samples() {
-            let mut amplitude = 0.0;
-            let num_samples = channel_samples.len();
+            //mono sums of the input and the tape return for the spectrum analyser
+            let mut analyzer_input = 0.0;
+            let mut analyzer_return = 0.0;
 
             //get input
-            let gain = self.params.gain.smoothed.next();
+            let input_to_tape = self.params.input_to_tape.smoothed.next();
+            let mut dry_level = self.params.dry_level.smoothed.next();
+            if self.params.kill_dry.value() {
+                dry_level = 0.0;
+            }
+            let wet_level = self.params.wet_level.smoothed.next();
 
             self.es.lg = self.params.low_gain.smoothed.next();
             self.es.mg = self.params.mid_gain.smoothed.next();
             self.es.hg = self.params.high_gain.smoothed.next();
+            self.es.crossover = self.params.crossover.value();
+            self.es.solo = [
+                self.params.low_solo.value(),
+                self.params.mid_solo.value(),
+                self.params.high_solo.value(),
+            ];
+            self.es.mute = [
+                self.params.low_mute.value(),
+                self.params.mid_mute.value(),
+                self.params.high_mute.value(),
+            ];
+
+            for (band, band_params) in self.peq.bands.iter_mut().zip(self.params.bands.iter()) {
+                band.enabled = band_params.enabled.value();
+                band.band_type = band_params.band_type.value();
+                band.frequency = band_params.frequency.smoothed.next();
+                band.gain_db = band_params.gain.smoothed.next();
+                band.q = band_params.q.smoothed.next();
+            }
+            self.es
+                .set_lowband_frequency(self.params.low_freq.smoothed.next());
+            self.es
+                .set_highband_frequency(self.params.high_freq.smoothed.next());
 
             self.tape
                 .set_tape_length(self.params.tape_length.smoothed.next());
             self.tape
                 .set_tape_speed(self.params.tape_speed.smoothed.next());
+            self.tape.set_speed_octave(self.params.speed_octave.value());
+
+            self.grains.size_ms = self.params.grain_size.smoothed.next();
+            self.grains.density = self.params.grain_density.smoothed.next();
+            self.grains.spray_ms = self.params.grain_spray.smoothed.next();
+            self.grains.pitch = self.params.grain_pitch.smoothed.next();
+            self.grains.reverse_chance = self.params.grain_reverse.smoothed.next();
+
+            self.slices.advance(&self.tape);
+
+            self.svf.cutoff = self.params.filter_cutoff.smoothed.next();
+            self.svf.resonance = self.params.filter_resonance.smoothed.next();
+            self.svf.lfo_rate = if self.params.lfo_sync.value() {
+                SVFSTATE::synced_rate(tempo, lfo_division)
+            } else {
+                self.params.lfo_rate.smoothed.next()
+            };
+            self.svf.lfo_depth = self.params.lfo_depth.smoothed.next();
+            self.svf.env_amount = self.params.env_amount.smoothed.next();
+            self.svf.env_attack_ms = self.params.env_attack.smoothed.next();
+            self.svf.env_release_ms = self.params.env_release.smoothed.next();
+            self.svf.advance_lfo();
+
+            self.tape.set_feedback(self.params.feedback.smoothed.next());
+
+            let head_mask = self.params.head_select.value().mask();
+            for (i, head) in self.tape.heads.iter_mut().enumerate() {
+                let head_params = &self.params.heads[i];
+
+                head.enabled = match head_mask {
+                    Some(mask) => mask[i],
+                    None => head_params.enabled.value(),
+                };
+                head.level = head_params.level.smoothed.next();
+                head.delay = head_params.delay.smoothed.next();
+            }
+
+            let multiband = self.params.multiband.value();
+            for (tape, loop_params) in self
+                .band_tapes
+                .iter_mut()
+                .zip(self.params.band_loops.iter())
+            {
+                tape.set_tape_length(loop_params.length.smoothed.next());
+                tape.set_tape_speed(loop_params.speed.smoothed.next());
+                tape.set_speed_octave(self.params.speed_octave.value());
+                tape.set_feedback(loop_params.feedback.smoothed.next());
+            }
+
+            let eq_placement = self.params.eq_placement.value();
+            self.es.zero_latency = eq_placement == EqPlacement::FeedbackLoop;
+
+            //TAPE, moves a frame at a time and each channel records and plays its own lane
+            let reverse = self.params.reverse.value();
+            let fast_forward = self.params.fast_forward.value();
+            let play = self.params.play_pause.value();
+
+            self.tape.run_transport(reverse, fast_forward, play);
+            if multiband {
+                for tape in self.band_tapes.iter_mut() {
+                    tape.run_transport(reverse, fast_forward, play);
+                }
+            }
+            if self.params.granular.value() {
+                self.grains.advance(&self.tape);
+            }
 
             //processing
-            for sample in channel_samples {
+            for (channel, sample) in channel_samples.into_iter().enumerate() {
+                analyzer_input += *sample;
+
                 //EQ
-                self.es.process_3band(sample); //TODO - Loop degredation here
-
-                //TAPE
-                if self.params.reverse.value() == true {
-                    self.tape.dec_sample_idx();
-                } else if self.params.fast_forward.value() == true {
-                    self.tape.fast_forward();
-                } else if self.params.play_pause.value() == true {
-                    self.tape.inc_sample_idx(); //play normally
+                if eq_placement == EqPlacement::Input {
+                    if !multiband {
+                        self.es.process_3band(channel, sample);
+                    }
+                    self.peq.process(channel, sample);
                 }
 
                 //TODO - due for a refactor? we recheck all this again to make sure it plays when
                 //       we've pushed any button without copying the read writes in to each conditional
-                if self.params.reverse.value()
-                    || self.params.fast_forward.value()
-                    || self.params.play_pause.value()
-                {
-                    self.tape.to_buffer(sample, Some(gain));
-                    *sample += self.tape.from_buffer();
-                }
+                let mut tape_return = 0.0;
+                if (reverse || fast_forward || play) && multiband {
+                    //each band goes round its own loop, then they're weighed and summed back up
+                    let (l, m, h) = self.es.split_3band(channel, *sample);
+                    let mut returns = [0.0; 3];
+
+                    for ((tape, band), band_return) in self
+                        .band_tapes
+                        .iter_mut()
+                        .zip([l, m, h].iter_mut())
+                        .zip(returns.iter_mut())
+                    {
+                        tape.to_buffer(channel, band, Some(input_to_tape));
+                        *band_return = tape.from_buffer(channel);
+                    }
 
-                amplitude += *sample;
-            }
+                    let (l, m, h) = self.es.weigh_bands((returns[0], returns[1], returns[2]));
+                    tape_return = l + m + h;
 
-            //crunch some stuff if the plugin window is open
-            if self.params.editor_state.is_open() {
-                amplitude = (amplitude / num_samples as f32).abs();
-                let current_peak_meter = self.peak_meter.load(std::sync::atomic::Ordering::Relaxed);
+                    //the bands can't go back through the EQ that split them, only the parametric
+                    if eq_placement != EqPlacement::Input {
+                        self.peq.process(channel, &mut tape_return);
+                    }
 
-                let new_peak_meter = if amplitude > current_peak_meter {
-                    amplitude
-                } else {
-                    current_peak_meter * self.peak_meter_decay_weight
-                        + amplitude * (1.0 - self.peak_meter_decay_weight)
-                };
+                    self.tape.print(channel, tape_return);
+                } else if reverse || fast_forward || play {
+                    tape_return = if self.params.echo.value() {
+                        //heads read first, then the record head lays input + echo back down
+                        let echo = self.tape.echo(channel);
+                        let mut echo_feedback = echo;
+                        if eq_placement == EqPlacement::FeedbackLoop {
+                            self.es.process_3band(channel, &mut echo_feedback);
+                            self.peq.process(channel, &mut echo_feedback);
+                        }
+
+                        self.tape.to_buffer_echo(
+                            channel,
+                            sample,
+                            Some(input_to_tape),
+                            echo_feedback,
+                        );
+                        echo
+                    } else {
+                        //loop degradation, every pass goes through the EQ again
+                        if eq_placement == EqPlacement::FeedbackLoop {
+                            let es = &mut self.es;
+                            let peq = &mut self.peq;
+                            self.tape.filter_under_head(channel, |mut on_tape| {
+                                es.process_3band(channel, &mut on_tape);
+                                peq.process(channel, &mut on_tape);
+                                on_tape
+                            });
+                        }
+
+                        self.tape.to_buffer(channel, sample, Some(input_to_tape));
+
+                        if self.params.stutter.value() {
+                            self.slices.read(&self.tape, channel)
+                        } else if self.params.granular.value() {
+                            self.grains.read(&self.tape, channel)
+                        } else {
+                            self.tape.from_buffer(channel)
+                        }
+                    };
 
-                self.peak_meter
-                    .store(new_peak_meter, std::sync::atomic::Ordering::Relaxed);
+                    if eq_placement == EqPlacement::TapeReturn {
+                        self.es.process_3band(channel, &mut tape_return);
+                        self.peq.process(channel, &mut tape_return);
+                    }
+                }
+
+                if (reverse || fast_forward || play) && self.params.filter.value() {
+                    self.svf.process(channel, &mut tape_return);
+                }
 
-                self.tape_pos.store(
-                    self.tape.current_position_percent(),
-                    std::sync::atomic::Ordering::Relaxed,
+                analyzer_return += tape_return;
+                *sample = *sample * dry_level + tape_return * wet_level;
+                self.meter.process(channel, *sample);
+            }
+            self.waveform.update(&self.tape, &self.waveform_data);
+            self.meter.end_frame();
+
+            if editor_open {
+                self.analyzer_data.push(
+                    analyzer_input / num_channels,
+                    analyzer_return / num_channels,
                 );
             }
         }
 
+        //crunch some stuff if the plugin window is open
+        if editor_open {
+            self.meter.publish(&self.meter_data);
+            self.waveform
+                .publish_markers(&self.tape, &self.waveform_data);
+
+            self.tape_pos.store(
+                self.tape.current_position_percent(),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+            self.tape_velocity
+                .store(self.tape.velocity(), std::sync::atomic::Ordering::Relaxed);
+        }
+
         return ProcessStatus::Normal;
     }
 }
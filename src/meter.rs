@@ -0,0 +1,197 @@
+//level metering, METERSTATE runs on the audio thread and publishes into MeterData which the editor
+//reads from. everything shared is atomics so neither side ever has to wait on the other
+
+use crate::biquad::BIQUAD;
+use crate::NUM_CHANNELS;
+use atomic_float::AtomicF32;
+use nih_plug::util;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+//anything at or over full scale lights the clip light
+pub const CLIP_LEVEL: f32 = 1.0;
+
+/// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
+const PEAK_METER_DECAY_MS: f32 = 150.0;
+const RMS_WINDOW_MS: f32 = 300.0;
+const PEAK_HOLD_MS: f32 = 1500.0;
+//short term loudness is the mean over the last 3 seconds, kept as 30 blocks of 100 ms
+const SHORT_TERM_BLOCKS: usize = 30;
+const SHORT_TERM_BLOCK_MS: f32 = 100.0;
+
+pub struct ChannelMeter {
+    pub peak: AtomicF32,
+    pub rms: AtomicF32,
+    pub hold: AtomicF32,
+    //latches until the editor resets it
+    pub clip: AtomicBool,
+}
+
+impl Default for ChannelMeter {
+    fn default() -> Self {
+        Self {
+            peak: AtomicF32::new(0.0),
+            rms: AtomicF32::new(0.0),
+            hold: AtomicF32::new(0.0),
+            clip: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Meter readings shared with the editor, levels are linear gain.
+pub struct MeterData {
+    pub channels: [ChannelMeter; NUM_CHANNELS],
+    pub short_term_lufs: AtomicF32,
+}
+
+impl Default for MeterData {
+    fn default() -> Self {
+        Self {
+            channels: [ChannelMeter::default(), ChannelMeter::default()],
+            short_term_lufs: AtomicF32::new(util::MINUS_INFINITY_DB),
+        }
+    }
+}
+
+impl MeterData {
+    pub fn reset_clip(&self) {
+        for channel in self.channels.iter() {
+            channel.clip.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct METERSTATE {
+    peak_decay_weight: f32,
+    rms_weight: f32,
+    hold_len: usize,
+
+    peak: [f32; NUM_CHANNELS],
+    mean_square: [f32; NUM_CHANNELS],
+    hold: [f32; NUM_CHANNELS],
+    hold_countdown: [usize; NUM_CHANNELS],
+    clipped: [bool; NUM_CHANNELS],
+
+    //K-weighting for the loudness measurement, a high shelf and a high pass per channel
+    k_shelf: [BIQUAD; NUM_CHANNELS],
+    k_highpass: [BIQUAD; NUM_CHANNELS],
+    block_sum: f32,
+    block_samples: usize,
+    block_len: usize,
+    short_term: [f32; SHORT_TERM_BLOCKS],
+    short_term_idx: usize,
+}
+
+impl Default for METERSTATE {
+    fn default() -> Self {
+        Self {
+            peak_decay_weight: 1.0,
+            rms_weight: 1.0,
+            hold_len: 0,
+
+            peak: [0.0; NUM_CHANNELS],
+            mean_square: [0.0; NUM_CHANNELS],
+            hold: [0.0; NUM_CHANNELS],
+            hold_countdown: [0; NUM_CHANNELS],
+            clipped: [false; NUM_CHANNELS],
+
+            k_shelf: [BIQUAD::default(); NUM_CHANNELS],
+            k_highpass: [BIQUAD::default(); NUM_CHANNELS],
+            block_sum: 0.0,
+            block_samples: 0,
+            block_len: 4410,
+            short_term: [0.0; SHORT_TERM_BLOCKS],
+            short_term_idx: 0,
+        }
+    }
+}
+
+impl METERSTATE {
+    pub fn init(&mut self, samplerate: f32) {
+        // After `PEAK_METER_DECAY_MS` milliseconds of pure silence, the peak meter's value should
+        // have dropped by 12 dB
+        self.peak_decay_weight = 0.25f32.powf((samplerate * PEAK_METER_DECAY_MS / 1000.0).recip());
+        self.rms_weight = f32::exp(-1.0 / (samplerate * RMS_WINDOW_MS / 1000.0));
+        self.hold_len = (samplerate * PEAK_HOLD_MS / 1000.0) as usize;
+
+        //close to the BS.1770 pre-filter, +4 dB above ~1.5 kHz and rolled off below ~40 Hz
+        for (shelf, highpass) in self.k_shelf.iter_mut().zip(self.k_highpass.iter_mut()) {
+            shelf.set_highshelf(samplerate, 1500.0, 0.71, 4.0);
+            highpass.set_highpass(samplerate, 38.0, 0.5);
+            shelf.reset();
+            highpass.reset();
+        }
+
+        self.block_len = (samplerate * SHORT_TERM_BLOCK_MS / 1000.0) as usize;
+        self.block_sum = 0.0;
+        self.block_samples = 0;
+        self.short_term = [0.0; SHORT_TERM_BLOCKS];
+    }
+
+    pub fn process(&mut self, channel: usize, sample: f32) {
+        if channel >= NUM_CHANNELS {
+            return;
+        }
+
+        let level = sample.abs();
+        if level >= CLIP_LEVEL {
+            self.clipped[channel] = true;
+        }
+
+        self.peak[channel] = if level > self.peak[channel] {
+            level
+        } else {
+            self.peak[channel] * self.peak_decay_weight + level * (1.0 - self.peak_decay_weight)
+        };
+
+        self.mean_square[channel] =
+            sample * sample + self.rms_weight * (self.mean_square[channel] - sample * sample);
+
+        if self.peak[channel] >= self.hold[channel] {
+            self.hold[channel] = self.peak[channel];
+            self.hold_countdown[channel] = self.hold_len;
+        } else if self.hold_countdown[channel] > 0 {
+            self.hold_countdown[channel] -= 1;
+        } else {
+            self.hold[channel] = self.peak[channel];
+        }
+
+        let weighted = self.k_highpass[channel].process(self.k_shelf[channel].process(sample));
+        self.block_sum += weighted * weighted;
+    }
+
+    /// Call once every frame after all channels have been through `process`.
+    pub fn end_frame(&mut self) {
+        self.block_samples += 1;
+        if self.block_samples >= self.block_len {
+            self.short_term[self.short_term_idx] = self.block_sum / self.block_samples as f32;
+            self.short_term_idx = (self.short_term_idx + 1) % SHORT_TERM_BLOCKS;
+            self.block_sum = 0.0;
+            self.block_samples = 0;
+        }
+    }
+
+    pub fn publish(&mut self, data: &MeterData) {
+        for (channel, meter) in data.channels.iter().enumerate() {
+            meter.peak.store(self.peak[channel], Ordering::Relaxed);
+            meter
+                .rms
+                .store(self.mean_square[channel].sqrt(), Ordering::Relaxed);
+            meter.hold.store(self.hold[channel], Ordering::Relaxed);
+
+            //only ever set from here, the editor is the one that clears it
+            if self.clipped[channel] {
+                meter.clip.store(true, Ordering::Relaxed);
+                self.clipped[channel] = false;
+            }
+        }
+
+        let mean_square = self.short_term.iter().sum::<f32>() / SHORT_TERM_BLOCKS as f32;
+        let lufs = if mean_square > 0.0 {
+            -0.691 + 10.0 * mean_square.log10()
+        } else {
+            util::MINUS_INFINITY_DB
+        };
+        data.short_term_lufs.store(lufs, Ordering::Relaxed);
+    }
+}
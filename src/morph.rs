@@ -0,0 +1,129 @@
+//A/B snapshots and morphing between them. the snapshots are kept like presets, plain values by
+//parameter ID, and MORPHSTATE moves every float parameter's smoother between the two on the audio
+//thread so the morph can be automated with the editor closed
+
+use crate::presets::Preset;
+use nih_plug::prelude::{Param, ParamPtr, ParamSetter, Params};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The morph amount can't morph itself.
+const MORPH_ID: &str = "morph";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Snapshots {
+    //A and B, empty until something is stored in them
+    pub slots: [BTreeMap<String, f32>; 2],
+    pub active: usize,
+}
+
+impl Snapshots {
+    //same as a preset, which leaves the morph controls alone
+    fn capture(params: &impl Params) -> BTreeMap<String, f32> {
+        Preset::capture("", "", params).params
+    }
+
+    /// Keep the current settings in the active slot.
+    pub fn store_active(&mut self, params: &impl Params) {
+        self.slots[self.active] = Self::capture(params);
+    }
+
+    /// Switch to `slot`, keeping the current settings in the slot being left. An empty slot starts
+    /// out as a copy of the other one.
+    pub fn select(&mut self, slot: usize, params: &impl Params, setter: &ParamSetter) {
+        if slot == self.active {
+            return;
+        }
+
+        self.store_active(params);
+        if self.slots[slot].is_empty() {
+            self.slots[slot] = self.slots[self.active].clone();
+        }
+
+        Preset {
+            params: self.slots[slot].clone(),
+            ..Default::default()
+        }
+        .apply(params, setter);
+        self.active = slot;
+    }
+
+    /// Copy the current settings over the other slot as well.
+    pub fn copy_active(&mut self, params: &impl Params) {
+        self.store_active(params);
+        self.slots[1 - self.active] = self.slots[self.active].clone();
+    }
+}
+
+/// The audio thread's half. Snapshot values are kept normalized so skewed parameters morph
+/// evenly, NaN where a snapshot doesn't have the parameter.
+#[derive(Default)]
+pub struct MORPHSTATE {
+    ids: Vec<String>,
+    targets: Vec<ParamPtr>,
+    a: Vec<f32>,
+    b: Vec<f32>,
+    engaged: bool,
+}
+
+impl MORPHSTATE {
+    /// Find every float parameter that can morph, allocates so it's not for the audio thread.
+    pub fn init(&mut self, params: &impl Params) {
+        (self.ids, self.targets) = params
+            .param_map()
+            .into_iter()
+            .filter(|(id, ptr, _)| matches!(ptr, ParamPtr::FloatParam(_)) && id != MORPH_ID)
+            .map(|(id, ptr, _)| (id, ptr))
+            .unzip();
+
+        self.a = vec![f32::NAN; self.targets.len()];
+        self.b = vec![f32::NAN; self.targets.len()];
+    }
+
+    /// Pick up new snapshots, doesn't allocate.
+    pub fn refresh(&mut self, snapshots: &Snapshots) {
+        for (i, (id, ptr)) in self.ids.iter().zip(self.targets.iter()).enumerate() {
+            let normalized = |slot: &BTreeMap<String, f32>| match slot.get(id) {
+                Some(plain) => unsafe { ptr.preview_normalized(*plain) },
+                None => f32::NAN,
+            };
+
+            self.a[i] = normalized(&snapshots.slots[0]);
+            self.b[i] = normalized(&snapshots.slots[1]);
+        }
+    }
+
+    /// Once per block. `amount` is the morph from A to B, `None` when morphing is off, in which
+    /// case the parameters go back to their own values.
+    pub fn process(&mut self, amount: Option<f32>, sample_rate: f32) {
+        match amount {
+            Some(amount) => {
+                self.engaged = true;
+
+                for ((ptr, a), b) in self.targets.iter().zip(self.a.iter()).zip(self.b.iter()) {
+                    if let (ParamPtr::FloatParam(param), false) = (ptr, a.is_nan() || b.is_nan()) {
+                        unsafe {
+                            let plain = ptr.preview_plain(a + (b - a) * amount);
+                            (**param).smoothed.set_target(sample_rate, plain);
+                        }
+                    }
+                }
+            }
+            None if self.engaged => {
+                self.engaged = false;
+
+                for ptr in self.targets.iter() {
+                    if let ParamPtr::FloatParam(param) = ptr {
+                        unsafe {
+                            (**param)
+                                .smoothed
+                                .set_target(sample_rate, (**param).value())
+                        };
+                    }
+                }
+            }
+            None => (),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//parametric EQ, a cascade of RBJ biquads that sits after the three band EQ
+
+use crate::biquad::BIQUAD;
+use crate::NUM_CHANNELS;
+use nih_plug::prelude::Enum;
+
+pub const NUM_BANDS: usize = 4;
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum BandType {
+    #[id = "low shelf"]
+    #[name = "low shelf"]
+    LowShelf,
+    #[id = "high shelf"]
+    #[name = "high shelf"]
+    HighShelf,
+    #[id = "peak"]
+    #[name = "peak"]
+    Peak,
+    #[id = "low pass"]
+    #[name = "low pass"]
+    LowPass,
+    #[id = "high pass"]
+    #[name = "high pass"]
+    HighPass,
+    #[id = "notch"]
+    #[name = "notch"]
+    Notch,
+}
+
+#[derive(Clone, Copy)]
+pub struct BAND {
+    pub enabled: bool,
+    pub band_type: BandType,
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q: f32,
+
+    //one per channel, all with the same coefficients
+    filters: [BIQUAD; NUM_CHANNELS],
+    //what the filters' coefficients were last worked out for
+    current: Option<(BandType, f32, f32, f32)>,
+}
+
+impl Default for BAND {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            band_type: BandType::Peak,
+            frequency: 1000.0,
+            gain_db: 0.0,
+            q: 0.707,
+
+            filters: [BIQUAD::default(); NUM_CHANNELS],
+            current: None,
+        }
+    }
+}
+
+impl BAND {
+    fn update(&mut self, samplerate: f32) {
+        let settings = (self.band_type, self.frequency, self.gain_db, self.q);
+        if self.current == Some(settings) {
+            return;
+        }
+        self.current = Some(settings);
+
+        for filter in self.filters.iter_mut() {
+            match self.band_type {
+                BandType::LowShelf => {
+                    filter.set_lowshelf(samplerate, self.frequency, self.q, self.gain_db)
+                }
+                BandType::HighShelf => {
+                    filter.set_highshelf(samplerate, self.frequency, self.q, self.gain_db)
+                }
+                BandType::Peak => filter.set_peak(samplerate, self.frequency, self.q, self.gain_db),
+                BandType::LowPass => filter.set_lowpass(samplerate, self.frequency, self.q),
+                BandType::HighPass => filter.set_highpass(samplerate, self.frequency, self.q),
+                BandType::Notch => filter.set_notch(samplerate, self.frequency, self.q),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PARAMETRICSTATE {
+    pub bands: [BAND; NUM_BANDS],
+    sr: f32,
+}
+
+impl Default for PARAMETRICSTATE {
+    fn default() -> Self {
+        Self {
+            bands: [BAND::default(); NUM_BANDS],
+            sr: 44100.0,
+        }
+    }
+}
+
+impl PARAMETRICSTATE {
+    pub fn init(&mut self, samplerate: f32) {
+        self.sr = samplerate;
+
+        for band in self.bands.iter_mut() {
+            for filter in band.filters.iter_mut() {
+                filter.reset();
+            }
+            band.current = None;
+        }
+    }
+
+    pub fn process(&mut self, channel: usize, sample: &mut f32) {
+        for band in self.bands.iter_mut().filter(|band| band.enabled) {
+            //only redoes the coefficients if the band's settings moved
+            band.update(self.sr);
+            *sample = band.filters[channel].process(*sample);
+        }
+    }
+}
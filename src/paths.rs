@@ -0,0 +1,21 @@
+//where the plugin keeps its files on disk
+
+use std::path::PathBuf;
+
+const DIR_NAME: &str = "miso_paste";
+
+/// The plugin's per user directory, `None` if the platform's home/app data isn't set.
+pub fn user_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config) => PathBuf::from(config),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        }
+    };
+
+    Some(base.join(DIR_NAME))
+}
@@ -0,0 +1,478 @@
+//presets. a preset is every parameter's plain value by ID saved as JSON in the user directory, the
+//tape can go along with it as a file of raw little endian 32 bit floats next to the JSON
+
+use crate::paths;
+use crate::tapeloop::TAPESTATE;
+use crate::NUM_CHANNELS;
+use atomic_float::AtomicF32;
+use nih_plug::nih_log;
+use nih_plug::prelude::{ParamSetter, Params};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Transport, one shot buttons and the A/B morph, these never go in a preset.
+const SKIPPED_IDS: [&str; 7] = [
+    "clear",
+    "undo",
+    "reverse",
+    "fast forward",
+    "play / pause",
+    "morph on",
+    "morph",
+];
+
+/// Parameter values are plain values, enums by their index and bools as 0 or 1.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Preset {
+    pub name: String,
+    pub category: String,
+    pub params: BTreeMap<String, f32>,
+    //file name of the tape next to the preset and the sample rate it was recorded at
+    pub tape: Option<String>,
+    pub tape_sample_rate: Option<f32>,
+}
+
+impl Preset {
+    pub fn capture(name: &str, category: &str, params: &impl Params) -> Self {
+        Self {
+            name: name.to_owned(),
+            category: category.to_owned(),
+            params: params
+                .param_map()
+                .into_iter()
+                .filter(|(id, _, _)| !SKIPPED_IDS.contains(&id.as_str()))
+                .map(|(id, ptr, _)| (id, unsafe { ptr.unmodulated_plain_value() }))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Set every parameter from the preset, anything it doesn't mention goes back to its default.
+    pub fn apply(&self, params: &impl Params, setter: &ParamSetter) {
+        let context = setter.raw_context;
+
+        for (id, ptr, _) in params.param_map() {
+            if SKIPPED_IDS.contains(&id.as_str()) {
+                continue;
+            }
+
+            unsafe {
+                let normalized = match self.params.get(&id) {
+                    Some(plain) => ptr.preview_normalized(*plain),
+                    None => ptr.default_normalized_value(),
+                };
+
+                context.raw_begin_set_parameter(ptr);
+                context.raw_set_parameter_normalized(ptr, normalized);
+                context.raw_end_set_parameter(ptr);
+            }
+        }
+    }
+}
+
+/// The built in presets, read only. Only what differs from the defaults is listed.
+pub fn factory() -> Vec<Preset> {
+    let preset = |name: &str, category: &str, values: &[(&str, f32)]| Preset {
+        name: name.to_owned(),
+        category: category.to_owned(),
+        params: values
+            .iter()
+            .map(|(id, value)| (id.to_string(), *value))
+            .collect(),
+        ..Default::default()
+    };
+
+    vec![
+        preset(
+            "Clean Looper",
+            "looper",
+            &[("gain", 1.0), ("tape length", 8.0), ("feedback", 1.0)],
+        ),
+        preset(
+            "Worn Cassette",
+            "lo-fi",
+            &[
+                ("gain", 0.7),
+                ("tape speed", 0.97),
+                ("feedback", 0.85),
+                //feedback loop, so every pass wears the highs down a little more
+                ("eq placement", 2.0),
+                ("low gain", 0.8),
+                ("high gain", 0.5),
+                ("filter", 1.0),
+                ("filter cutoff", 6000.0),
+                ("lfo rate", 0.4),
+                ("lfo depth", 0.1),
+            ],
+        ),
+        preset(
+            "Dub Echo",
+            "delay",
+            &[
+                ("gain", 0.8),
+                ("echo", 1.0),
+                ("feedback", 0.6),
+                ("eq placement", 2.0),
+                ("low gain", 0.5),
+                ("high gain", 0.6),
+                ("head on_1", 1.0),
+                ("head delay_1", 0.375),
+                ("head on_2", 1.0),
+                ("head delay_2", 0.75),
+                ("head level_2", 0.6),
+                ("filter", 1.0),
+                //band pass
+                ("filter mode", 1.0),
+                ("filter cutoff", 1200.0),
+                ("filter resonance", 0.4),
+            ],
+        ),
+    ]
+}
+
+pub struct PresetEntry {
+    pub preset: Preset,
+    //None for the factory presets
+    pub path: Option<PathBuf>,
+}
+
+/// Factory presets followed by the user's, plus the browser's selection and save form.
+#[derive(Default)]
+pub struct PresetBrowser {
+    pub entries: Vec<PresetEntry>,
+    pub current: Option<usize>,
+    //None shows every category
+    pub category: Option<String>,
+
+    pub save_name: String,
+    pub save_category: String,
+    pub include_tape: bool,
+    //waiting on the audio thread for the tape
+    pub pending_save: Option<Preset>,
+    pub status: String,
+}
+
+impl PresetBrowser {
+    pub fn dir() -> Option<PathBuf> {
+        Some(paths::user_dir()?.join("presets"))
+    }
+
+    pub fn load() -> Self {
+        let mut browser = Self::default();
+        browser.reload();
+        browser
+    }
+
+    pub fn reload(&mut self) {
+        self.entries = factory()
+            .into_iter()
+            .map(|preset| PresetEntry { preset, path: None })
+            .collect();
+
+        let files = Self::dir()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"));
+
+        let mut user: Vec<PresetEntry> = files
+            .filter_map(|path| {
+                let preset = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<Preset>(&json).ok());
+                if preset.is_none() {
+                    nih_log!("couldn't read preset {}", path.display());
+                }
+
+                Some(PresetEntry {
+                    preset: preset?,
+                    path: Some(path),
+                })
+            })
+            .collect();
+        user.sort_by(|a, b| a.preset.name.cmp(&b.preset.name));
+
+        self.entries.extend(user);
+        self.current = self.current.filter(|current| *current < self.entries.len());
+    }
+
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| entry.preset.category.clone())
+            .filter(|category| !category.is_empty())
+            .collect();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
+
+    /// Indices of the entries in the selected category.
+    pub fn visible(&self) -> Vec<usize> {
+        (0..self.entries.len())
+            .filter(|i| match &self.category {
+                Some(category) => self.entries[*i].preset.category == *category,
+                None => true,
+            })
+            .collect()
+    }
+
+    /// The next or previous preset in the selected category, wrapping around.
+    pub fn step(&self, forward: bool) -> Option<usize> {
+        let visible = self.visible();
+        if visible.is_empty() {
+            return None;
+        }
+
+        let position = self
+            .current
+            .and_then(|current| visible.iter().position(|i| *i == current));
+        let next = match (position, forward) {
+            (None, true) => 0,
+            (None, false) => visible.len() - 1,
+            (Some(position), true) => (position + 1) % visible.len(),
+            (Some(position), false) => (position + visible.len() - 1) % visible.len(),
+        };
+
+        Some(visible[next])
+    }
+
+    /// Apply an entry, sending its tape over to the audio thread if it has one.
+    pub fn load_entry(
+        &mut self,
+        index: usize,
+        params: &impl Params,
+        setter: &ParamSetter,
+        transfer: &TapeTransfer,
+    ) {
+        self.current = Some(index);
+        self.entries[index].preset.apply(params, setter);
+
+        if let Some(samples) = self.read_tape(index) {
+            //tapes are saved at whatever rate the session was running at
+            let session_rate = transfer.sample_rate();
+            match self.entries[index].preset.tape_sample_rate {
+                Some(rate) if rate > 0.0 && session_rate > 0.0 && rate != session_rate => {
+                    transfer.request_load(&resample(&samples, rate, session_rate));
+                    self.status = format!("tape resampled from {rate} Hz");
+                }
+                _ => transfer.request_load(&samples),
+            }
+        }
+    }
+
+    /// Save the current settings under the form's name and category. With the tape included this
+    /// only asks the audio thread for it, `finish_pending_save` writes the files once it arrives.
+    pub fn start_save(&mut self, params: &impl Params, transfer: &TapeTransfer) {
+        let preset = Preset::capture(&self.save_name, &self.save_category, params);
+
+        if self.include_tape {
+            transfer.request_save();
+            self.pending_save = Some(preset);
+            self.status = "waiting for the tape".to_owned();
+        } else {
+            let result = self.save(preset, None);
+            self.report(result);
+        }
+    }
+
+    pub fn finish_pending_save(&mut self, transfer: &TapeTransfer) {
+        if self.pending_save.is_none() {
+            return;
+        }
+
+        if let Some((samples, sample_rate)) = transfer.take_saved() {
+            if let Some(preset) = self.pending_save.take() {
+                let result = self.save(preset, Some((&samples, sample_rate)));
+                self.report(result);
+            }
+        }
+    }
+
+    fn report(&mut self, result: std::io::Result<()>) {
+        self.status = match result {
+            Ok(()) => "saved".to_owned(),
+            Err(err) => format!("couldn't save: {err}"),
+        };
+    }
+
+    /// The tape saved with an entry, if it has one.
+    pub fn read_tape(&self, index: usize) -> Option<Vec<f32>> {
+        let entry = &self.entries[index];
+        let tape_path = entry
+            .path
+            .as_ref()?
+            .with_file_name(entry.preset.tape.as_ref()?);
+
+        match std::fs::read(&tape_path) {
+            Ok(bytes) => Some(
+                bytes
+                    .chunks_exact(4)
+                    .map(|sample| f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]))
+                    .collect(),
+            ),
+            Err(err) => {
+                nih_log!("couldn't read tape {}: {err}", tape_path.display());
+                None
+            }
+        }
+    }
+
+    /// Write a preset out, and its tape next to it if there is one, then pick it up in the list.
+    pub fn save(&mut self, mut preset: Preset, tape: Option<(&[f32], f32)>) -> std::io::Result<()> {
+        let dir = Self::dir().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no user directory")
+        })?;
+        std::fs::create_dir_all(&dir)?;
+
+        let file_name = file_name(&preset.name);
+        if let Some((samples, sample_rate)) = tape {
+            let tape_file = format!("{file_name}.tape");
+            let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+            std::fs::write(dir.join(&tape_file), bytes)?;
+
+            preset.tape = Some(tape_file);
+            preset.tape_sample_rate = Some(sample_rate);
+        }
+
+        let path = dir.join(format!("{file_name}.json"));
+        let json = serde_json::to_string_pretty(&preset)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        std::fs::write(&path, json)?;
+
+        self.reload();
+        self.current = self
+            .entries
+            .iter()
+            .position(|entry| entry.path.as_deref() == Some(path.as_path()));
+
+        Ok(())
+    }
+}
+
+//linear interpolation between frames, lane by lane. there's no filtering on the way down but it's
+//only ever a loop being carried between sessions
+fn resample(samples: &[f32], from: f32, to: f32) -> Vec<f32> {
+    let frames = samples.len() / NUM_CHANNELS;
+    if frames == 0 {
+        return Vec::new();
+    }
+
+    let step = from as f64 / to as f64;
+    let out_frames = (frames as f64 / step).round() as usize;
+    let mut out = Vec::with_capacity(out_frames * NUM_CHANNELS);
+    for n in 0..out_frames {
+        let position = n as f64 * step;
+        let idx = (position as usize).min(frames - 1);
+        let next_idx = (idx + 1).min(frames - 1);
+        let frac = (position - idx as f64) as f32;
+
+        for channel in 0..NUM_CHANNELS {
+            out.push(
+                samples[idx * NUM_CHANNELS + channel] * (1.0 - frac)
+                    + samples[next_idx * NUM_CHANNELS + channel] * frac,
+            );
+        }
+    }
+
+    return out;
+}
+
+//keep preset names from wandering out of the preset folder
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match name.trim() {
+        "" => "untitled".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+/// Hands the tape between the editor and the audio thread for presets that include it. The editor
+/// raises a request and the audio thread deals with it at the start of its next block. The buffer
+/// is reserved up front so the audio thread never allocates.
+#[derive(Default)]
+pub struct TapeTransfer {
+    audio: Mutex<Vec<f32>>,
+    sample_rate: AtomicF32,
+    save_requested: AtomicBool,
+    save_ready: AtomicBool,
+    load_requested: AtomicBool,
+}
+
+impl TapeTransfer {
+    /// Make room for `len` samples of tape recorded at the session's `sample_rate`.
+    pub fn reserve(&self, len: usize, sample_rate: f32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+
+        let mut audio = self.audio.lock().unwrap();
+        audio.clear();
+        audio.reserve_exact(len);
+    }
+
+    /// The session's sample rate, what the tape is saved at and loaded tapes are resampled to.
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn request_save(&self) {
+        self.save_ready.store(false, Ordering::Relaxed);
+        self.save_requested.store(true, Ordering::Release);
+    }
+
+    /// The tape and its sample rate once the audio thread has copied it out.
+    pub fn take_saved(&self) -> Option<(Vec<f32>, f32)> {
+        if !self.save_ready.swap(false, Ordering::Acquire) {
+            return None;
+        }
+
+        let audio = self.audio.lock().unwrap();
+        Some((audio.clone(), self.sample_rate.load(Ordering::Relaxed)))
+    }
+
+    pub fn request_load(&self, samples: &[f32]) {
+        let mut audio = self.audio.lock().unwrap();
+        //never past what the audio thread's copy has room for
+        let len = samples.len().min(audio.capacity());
+        audio.clear();
+        audio.extend_from_slice(&samples[..len]);
+
+        self.load_requested.store(true, Ordering::Release);
+    }
+
+    /// Audio thread side. If the editor is holding the lock the request waits for the next block.
+    pub fn service(&self, tape: &mut TAPESTATE) {
+        let save = self.save_requested.load(Ordering::Acquire);
+        let load = self.load_requested.load(Ordering::Acquire);
+        if !save && !load {
+            return;
+        }
+
+        if let Ok(mut audio) = self.audio.try_lock() {
+            if load {
+                tape.import(&audio);
+                self.load_requested.store(false, Ordering::Relaxed);
+            } else {
+                tape.export(&mut audio);
+                self.sample_rate.store(tape.samplerate(), Ordering::Relaxed);
+                self.save_requested.store(false, Ordering::Relaxed);
+                self.save_ready.store(true, Ordering::Release);
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//xorshift noise for the bits of the playback that want to be a little random, cheap enough to call
+//from the audio thread and never allocates
+
+#[derive(Clone, Copy)]
+pub struct XORSHIFT {
+    state: u32,
+}
+
+impl XORSHIFT {
+    /// `seed` mustn't be 0, xorshift never leaves 0 once it's there.
+    pub const fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    /// 0.0..1.0
+    pub fn random(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        return self.state as f32 / u32::MAX as f32;
+    }
+}
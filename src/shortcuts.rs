@@ -0,0 +1,70 @@
+//keyboard shortcuts for the editor, read once a frame and then applied alongside the buttons
+
+use nih_plug_egui::egui;
+
+/// Tape lengths in seconds for the number keys 1 to 9.
+pub const LENGTH_PRESETS: [f32; 9] = [2.0, 4.0, 6.0, 8.0, 10.0, 15.0, 20.0, 30.0, 60.0];
+
+/// Key and what it does, for the `?` overlay.
+pub const SHORTCUT_HELP: &[(&str, &str)] = &[
+    ("space", "play / pause"),
+    ("R (hold)", "reverse"),
+    ("F (hold)", "fast forward"),
+    ("backspace", "clear"),
+    ("ctrl + Z", "undo clear"),
+    ("1 - 9", "tape length 2, 4, 6, 8, 10, 15, 20, 30, 60 s"),
+    ("?", "show / hide this list"),
+];
+
+const NUMBER_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/// This frame's shortcuts. The held ones are true for as long as the key is down, the rest only
+/// on the frame the key goes down.
+#[derive(Default)]
+pub struct Shortcuts {
+    pub play_pause: bool,
+    pub reverse: bool,
+    pub fast_forward: bool,
+    pub clear: bool,
+    pub undo: bool,
+    pub tape_length: Option<f32>,
+    pub toggle_help: bool,
+}
+
+impl Shortcuts {
+    pub fn read(ctx: &egui::Context) -> Self {
+        //leave the keyboard alone while a value is being typed in
+        if ctx.wants_keyboard_input() {
+            return Self::default();
+        }
+
+        let input = ctx.input();
+
+        Self {
+            play_pause: input.key_pressed(egui::Key::Space),
+            reverse: input.key_down(egui::Key::R),
+            fast_forward: input.key_down(egui::Key::F),
+            clear: input.key_pressed(egui::Key::Backspace),
+            undo: input.modifiers.command && input.key_pressed(egui::Key::Z),
+            tape_length: NUMBER_KEYS
+                .iter()
+                .zip(LENGTH_PRESETS)
+                .find(|(key, _)| input.key_pressed(**key))
+                .map(|(_, length)| length),
+            toggle_help: input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Text(text) if text == "?")),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//runtime skins. a skin is a folder with a `skin.json` manifest naming its images and where the
+//widgets sit on the 512x256 design, anything the manifest leaves out or that fails to load falls
+//back to the embedded deck
+
+use crate::im::{load_image_from_memory, UiImages};
+use crate::paths;
+use nih_plug::nih_log;
+use nih_plug_egui::egui::ColorImage;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = "skin.json";
+
+/// Widget positions in design coordinates. Points are centres, `[min x, min y, max x, max y]` for
+/// the rects.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct SkinPositions {
+    pub reel_l: [f32; 2],
+    pub reel_r: [f32; 2],
+    pub reel_size: f32,
+    //top left of the vertical sliders
+    pub input_slider: [f32; 2],
+    pub length_slider: [f32; 2],
+    pub freeze: [f32; 2],
+    pub play: [f32; 2],
+    pub fast_forward: [f32; 2],
+    pub reverse: [f32; 2],
+    pub clear: [f32; 2],
+    pub edit: [f32; 2],
+    pub spectrum: [f32; 2],
+    //centre of the preset bar
+    pub presets: [f32; 2],
+    //centre of the A/B row
+    pub compare: [f32; 2],
+    pub waveform: [f32; 4],
+    pub meter: [f32; 4],
+}
+
+impl Default for SkinPositions {
+    fn default() -> Self {
+        Self {
+            reel_l: [246.0, 92.0],
+            reel_r: [420.0, 92.0],
+            reel_size: 172.0,
+            input_slider: [64.0, 60.0],
+            length_slider: [124.0, 60.0],
+            freeze: [160.0, 212.0],
+            play: [230.0, 212.0],
+            fast_forward: [300.0, 212.0],
+            reverse: [370.0, 212.0],
+            clear: [440.0, 212.0],
+            edit: [90.0, 236.0],
+            spectrum: [35.0, 236.0],
+            presets: [80.0, 20.0],
+            compare: [80.0, 44.0],
+            waveform: [169.0, 181.0, 496.0, 197.0],
+            meter: [169.0, 235.0, 496.0, 245.0],
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Manifest {
+    name: Option<String>,
+    //image paths are relative to the skin's folder
+    background: Option<PathBuf>,
+    reel_l: Option<PathBuf>,
+    reel_r: Option<PathBuf>,
+    positions: SkinPositions,
+}
+
+pub struct Skin {
+    pub images: UiImages,
+    pub positions: SkinPositions,
+}
+
+impl Skin {
+    /// The skin folder, `skin` in the user directory.
+    pub fn dir() -> Option<PathBuf> {
+        Some(paths::user_dir()?.join("skin"))
+    }
+
+    /// Load the user's skin if there is one, `defaults` being the embedded images.
+    pub fn load(defaults: &UiImages) -> Self {
+        match Self::dir() {
+            Some(dir) if dir.join(MANIFEST).is_file() => Self::load_from(&dir, defaults),
+            _ => Self {
+                images: defaults.clone(),
+                positions: SkinPositions::default(),
+            },
+        }
+    }
+
+    pub fn load_from(dir: &Path, defaults: &UiImages) -> Self {
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST))
+            .map_err(|err| err.to_string())
+            .and_then(|json| serde_json::from_str::<Manifest>(&json).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                nih_log!(
+                    "couldn't read the skin manifest in {}: {err}",
+                    dir.display()
+                );
+                Manifest::default()
+            });
+
+        if let Some(name) = &manifest.name {
+            nih_log!("loading skin '{name}'");
+        }
+
+        let image = |file: &Option<PathBuf>, default: &ColorImage| {
+            file.as_ref()
+                .and_then(|file| {
+                    let path = dir.join(file);
+                    let loaded = std::fs::read(&path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| {
+                            load_image_from_memory(&bytes).map_err(|err| err.to_string())
+                        });
+
+                    match loaded {
+                        Ok(image) => Some(image),
+                        Err(err) => {
+                            nih_log!("couldn't load skin image {}: {err}", path.display());
+                            None
+                        }
+                    }
+                })
+                .unwrap_or_else(|| default.clone())
+        };
+
+        Self {
+            images: UiImages {
+                background: image(&manifest.background, &defaults.background),
+                reel_l: image(&manifest.reel_l, &defaults.reel_l),
+                reel_r: image(&manifest.reel_r, &defaults.reel_r),
+            },
+            positions: manifest.positions,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//beat slicing/stutter over the tape loop, the loop is chopped into equal slices which are then
+//played back on the host's beat grid in a different order
+
+use crate::rng::XORSHIFT;
+use crate::tapeloop::TAPESTATE;
+use crate::NUM_CHANNELS;
+use nih_plug::prelude::Enum;
+
+//short fade at either end of a step so cutting between slices doesn't click
+const FADE_FRAMES: f64 = 64.0;
+//how far either side of an equal slice boundary we look for a transient
+const TRANSIENT_SEARCH_FRAMES: usize = 4096;
+const TRANSIENT_HOP_FRAMES: usize = 64;
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum SlicePattern {
+    #[id = "repeat"]
+    #[name = "repeat"]
+    Repeat,
+    #[id = "reverse"]
+    #[name = "reverse order"]
+    Reverse,
+    #[id = "shuffle"]
+    #[name = "shuffle"]
+    Shuffle,
+}
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum SliceMode {
+    #[id = "equal"]
+    #[name = "equal"]
+    Equal,
+    #[id = "transient"]
+    #[name = "transient"]
+    Transient,
+}
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum SliceDivision {
+    #[id = "sixteenth"]
+    #[name = "1/16"]
+    Sixteenth,
+    #[id = "eighth"]
+    #[name = "1/8"]
+    Eighth,
+    #[id = "quarter"]
+    #[name = "1/4"]
+    Quarter,
+    #[id = "half"]
+    #[name = "1/2"]
+    Half,
+    #[id = "bar"]
+    #[name = "1 bar"]
+    Bar,
+}
+
+impl SliceDivision {
+    pub fn beats(&self) -> f64 {
+        match self {
+            SliceDivision::Sixteenth => 0.25,
+            SliceDivision::Eighth => 0.5,
+            SliceDivision::Quarter => 1.0,
+            SliceDivision::Half => 2.0,
+            SliceDivision::Bar => 4.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SLICESTATE {
+    //which step of the pattern we're on and how far into it
+    step: usize,
+    step_pos: f64,
+    step_len: f64,
+    //start of the slice currently playing, in frames
+    slice_start: f64,
+    rng: XORSHIFT,
+
+    //slice controls
+    pub count: usize,
+    pub repeats: usize,
+    pub pattern: SlicePattern,
+    pub mode: SliceMode,
+}
+
+impl Default for SLICESTATE {
+    fn default() -> Self {
+        Self {
+            step: 0,
+            step_pos: 0.0,
+            step_len: 22050.0,
+            slice_start: 0.0,
+            rng: XORSHIFT::new(0x2545_F491),
+
+            count: 8,
+            repeats: 2,
+            pattern: SlicePattern::Repeat,
+            mode: SliceMode::Equal,
+        }
+    }
+}
+
+impl SLICESTATE {
+    pub fn init(&mut self) {
+        self.step = 0;
+        self.step_pos = 0.0;
+        self.slice_start = 0.0;
+    }
+
+    /// Set the length of a single step from the host tempo.
+    pub fn set_step_length(&mut self, samplerate: f32, tempo: f64, division: SliceDivision) {
+        self.step_len = samplerate as f64 * 60.0 / tempo * division.beats();
+    }
+
+    /// Lock the step counter to the host's position, `position` is measured in steps.
+    pub fn sync(&mut self, tape: &TAPESTATE, position: f64) {
+        let step = position.max(0.0).floor() as usize;
+        self.step_pos = position.fract() * self.step_len;
+
+        if step != self.step {
+            self.step = step;
+            self.pick_slice(tape);
+        }
+    }
+
+    /// Move one frame along the step, called once per frame. Everything here is counted in
+    /// frames, `read` then picks the channel.
+    pub fn advance(&mut self, tape: &TAPESTATE) {
+        self.step_pos += 1.0;
+        if self.step_pos >= self.step_len {
+            self.step_pos -= self.step_len;
+            self.step += 1;
+            self.pick_slice(tape);
+        }
+    }
+
+    pub fn read(&self, tape: &TAPESTATE, channel: usize) -> f32 {
+        let slice_len = self.slice_length(tape);
+
+        //if a step outlasts its slice the slice just loops
+        let offset = self.step_pos % slice_len;
+
+        let fade = f64::min(
+            1.0,
+            f64::min(self.step_pos, self.step_len - self.step_pos) / FADE_FRAMES,
+        );
+
+        return tape.sample_at(self.slice_start + offset, channel) * fade as f32;
+    }
+
+    fn slice_length(&self, tape: &TAPESTATE) -> f64 {
+        tape.loop_length() as f64 / self.count.max(1) as f64
+    }
+
+    fn pick_slice(&mut self, tape: &TAPESTATE) {
+        let count = self.count.max(1);
+        let group = self.step / self.repeats.max(1);
+
+        //only move to a new slice once the current one has been repeated enough
+        if self.step % self.repeats.max(1) != 0 {
+            return;
+        }
+
+        let slice = match self.pattern {
+            SlicePattern::Repeat => group % count,
+            SlicePattern::Reverse => count - 1 - group % count,
+            SlicePattern::Shuffle => (self.rng.random() * count as f32) as usize % count,
+        };
+
+        let start = slice as f64 * self.slice_length(tape);
+        self.slice_start = match self.mode {
+            SliceMode::Equal => start,
+            SliceMode::Transient => find_transient(tape, start as usize),
+        };
+    }
+}
+
+//look around `around` for the biggest jump in energy between two hops and return where it starts
+fn find_transient(tape: &TAPESTATE, around: usize) -> f64 {
+    let start = around as f64 - TRANSIENT_SEARCH_FRAMES as f64;
+    let hops = 2 * TRANSIENT_SEARCH_FRAMES / TRANSIENT_HOP_FRAMES;
+
+    let mut best = around as f64;
+    let mut best_rise = 0.0;
+    let mut last_energy = 0.0;
+
+    for hop in 0..hops {
+        let hop_start = start + (hop * TRANSIENT_HOP_FRAMES) as f64;
+
+        let mut energy = 0.0;
+        for i in 0..TRANSIENT_HOP_FRAMES {
+            for channel in 0..NUM_CHANNELS {
+                let sample = tape.sample_at(hop_start + i as f64, channel);
+                energy += sample * sample;
+            }
+        }
+
+        if hop > 0 && energy - last_energy > best_rise {
+            best_rise = energy - last_energy;
+            best = hop_start;
+        }
+        last_energy = energy;
+    }
+
+    return best.rem_euclid(tape.loop_length() as f64);
+}
@@ -0,0 +1,186 @@
+//state variable filter on the tape return, after Andrew Simper's trapezoidal SVF:
+//https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf
+//the cutoff is swept by an internal LFO and an envelope follower on the filter's input
+
+use crate::NUM_CHANNELS;
+use nih_plug::prelude::Enum;
+use std::f32::consts::PI;
+
+const MIN_CUTOFF: f32 = 20.0;
+const MAX_CUTOFF: f32 = 20000.0;
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum FilterMode {
+    #[id = "lowpass"]
+    #[name = "low pass"]
+    LowPass,
+    #[id = "bandpass"]
+    #[name = "band pass"]
+    BandPass,
+    #[id = "highpass"]
+    #[name = "high pass"]
+    HighPass,
+    #[id = "notch"]
+    #[name = "notch"]
+    Notch,
+}
+
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum LfoDivision {
+    #[id = "4 bars"]
+    #[name = "4 bars"]
+    FourBars,
+    #[id = "2 bars"]
+    #[name = "2 bars"]
+    TwoBars,
+    #[id = "1 bar"]
+    #[name = "1 bar"]
+    Bar,
+    #[id = "1/2"]
+    #[name = "1/2"]
+    Half,
+    #[id = "1/4"]
+    #[name = "1/4"]
+    Quarter,
+    #[id = "1/8"]
+    #[name = "1/8"]
+    Eighth,
+    #[id = "1/16"]
+    #[name = "1/16"]
+    Sixteenth,
+}
+
+impl LfoDivision {
+    pub fn beats(&self) -> f64 {
+        match self {
+            LfoDivision::FourBars => 16.0,
+            LfoDivision::TwoBars => 8.0,
+            LfoDivision::Bar => 4.0,
+            LfoDivision::Half => 2.0,
+            LfoDivision::Quarter => 1.0,
+            LfoDivision::Eighth => 0.5,
+            LfoDivision::Sixteenth => 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SVFSTATE {
+    //filter integrator state, per channel
+    ic1eq: [f32; NUM_CHANNELS],
+    ic2eq: [f32; NUM_CHANNELS],
+
+    //lfo phase, 0.0..1.0
+    lfo_phase: f64,
+    //each channel follows its own input
+    envelope: [f32; NUM_CHANNELS],
+
+    //filter controls
+    pub mode: FilterMode,
+    pub cutoff: f32,
+    pub resonance: f32,
+    //lfo controls, depth and envelope amount are in octaves
+    pub lfo_rate: f32,
+    pub lfo_depth: f32,
+    pub env_amount: f32,
+    pub env_attack_ms: f32,
+    pub env_release_ms: f32,
+
+    sr: f32,
+}
+
+impl Default for SVFSTATE {
+    fn default() -> Self {
+        Self {
+            ic1eq: [0.0; NUM_CHANNELS],
+            ic2eq: [0.0; NUM_CHANNELS],
+
+            lfo_phase: 0.0,
+            envelope: [0.0; NUM_CHANNELS],
+
+            mode: FilterMode::LowPass,
+            cutoff: 2000.0,
+            resonance: 0.2,
+            lfo_rate: 0.5,
+            lfo_depth: 0.0,
+            env_amount: 0.0,
+            env_attack_ms: 10.0,
+            env_release_ms: 200.0,
+
+            sr: 44100.0,
+        }
+    }
+}
+
+impl SVFSTATE {
+    pub fn init(&mut self, samplerate: f32) {
+        self.sr = samplerate;
+        self.ic1eq = [0.0; NUM_CHANNELS];
+        self.ic2eq = [0.0; NUM_CHANNELS];
+        self.lfo_phase = 0.0;
+        self.envelope = [0.0; NUM_CHANNELS];
+    }
+
+    /// Lock the LFO to the host, one cycle every `division`.
+    pub fn sync_lfo(&mut self, pos_beats: f64, division: LfoDivision) {
+        self.lfo_phase = (pos_beats / division.beats()).rem_euclid(1.0);
+    }
+
+    /// LFO rate in Hz for a tempo synced division.
+    pub fn synced_rate(tempo: f64, division: LfoDivision) -> f32 {
+        (tempo / 60.0 / division.beats()) as f32
+    }
+
+    /// Move the LFO along by one sample, called once per frame.
+    pub fn advance_lfo(&mut self) {
+        self.lfo_phase = (self.lfo_phase + self.lfo_rate as f64 / self.sr as f64).fract();
+    }
+
+    /// Filter one of `channel`'s samples, called once per channel every frame.
+    pub fn process(&mut self, channel: usize, sample: &mut f32) {
+        let c = channel;
+
+        //envelope follower
+        let level = sample.abs();
+        let time_ms = if level > self.envelope[c] {
+            self.env_attack_ms
+        } else {
+            self.env_release_ms
+        };
+        let coeff = f32::exp(-1.0 / (time_ms.max(0.1) / 1000.0 * self.sr));
+        self.envelope[c] = level + coeff * (self.envelope[c] - level);
+
+        //modulate the cutoff in octaves so sweeps sound even across the range
+        let lfo = f32::sin(2.0 * PI * self.lfo_phase as f32);
+        let octaves = lfo * self.lfo_depth + self.envelope[c] * self.env_amount;
+        let cutoff = f32::clamp(
+            self.cutoff * 2.0_f32.powf(octaves),
+            MIN_CUTOFF,
+            f32::min(MAX_CUTOFF, self.sr * 0.49),
+        );
+
+        let g = f32::tan(PI * cutoff / self.sr);
+        //resonance 0..1 maps onto a damping of 2..0.05, right up to self oscillation
+        let k = 2.0 - 1.95 * self.resonance.clamp(0.0, 1.0);
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = *sample - self.ic2eq[c];
+        let v1 = a1 * self.ic1eq[c] + a2 * v3;
+        let v2 = self.ic2eq[c] + a2 * self.ic1eq[c] + a3 * v3;
+        self.ic1eq[c] = 2.0 * v1 - self.ic1eq[c];
+        self.ic2eq[c] = 2.0 * v2 - self.ic2eq[c];
+
+        let low = v2;
+        let band = v1;
+        let high = *sample - k * v1 - v2;
+
+        *sample = match self.mode {
+            FilterMode::LowPass => low,
+            FilterMode::BandPass => band,
+            FilterMode::HighPass => high,
+            FilterMode::Notch => low + high,
+        };
+    }
+}
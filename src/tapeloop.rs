@@ -1,15 +1,152 @@
+use crate::NUM_CHANNELS;
 use nih_plug::nih_dbg;
+use nih_plug::prelude::Enum;
 
 const MAX_TAPE_LENGTH: f32 = 60.0;
 const MAX_TAPE_SPEED: f32 = 2.0;
+pub const NUM_HEADS: usize = 4;
+//how much of a freeze window is spent crossfading back into its start
+const FREEZE_CROSSFADE: f64 = 0.25;
+
+/// Quantised speed switches. Every step is an exact octave, so the loop boundaries stay where they
+/// are on the tape and a loop cut to the bar keeps lining up with the host, it just takes twice (or
+/// half) as many bars to come back around. While the tape is running a switch waits for the loop to
+/// wrap before it takes, otherwise switching mid loop would push every wrap after it off the bar.
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum SpeedOctave {
+    #[id = "quarter"]
+    #[name = "1/4x"]
+    Quarter,
+    #[id = "half"]
+    #[name = "1/2x"]
+    Half,
+    #[id = "unity"]
+    #[name = "1x"]
+    Unity,
+    #[id = "double"]
+    #[name = "2x"]
+    Double,
+}
+
+impl SpeedOctave {
+    pub fn ratio(&self) -> f32 {
+        match self {
+            SpeedOctave::Quarter => 0.25,
+            SpeedOctave::Half => 0.5,
+            SpeedOctave::Unity => 1.0,
+            SpeedOctave::Double => 2.0,
+        }
+    }
+}
+
+/// Which playback heads are reading in echo mode, laid out like the mode selector on a Space Echo.
+/// `Custom` leaves it to each head's own on/off switch.
+#[derive(Enum, Debug, PartialEq, Clone, Copy)]
+pub enum HeadSelect {
+    #[id = "custom"]
+    #[name = "custom"]
+    Custom,
+    #[id = "1"]
+    #[name = "1"]
+    One,
+    #[id = "2"]
+    #[name = "2"]
+    Two,
+    #[id = "3"]
+    #[name = "3"]
+    Three,
+    #[id = "4"]
+    #[name = "4"]
+    Four,
+    #[id = "1+2"]
+    #[name = "1+2"]
+    OneTwo,
+    #[id = "2+3"]
+    #[name = "2+3"]
+    TwoThree,
+    #[id = "3+4"]
+    #[name = "3+4"]
+    ThreeFour,
+    #[id = "1+2+3"]
+    #[name = "1+2+3"]
+    OneTwoThree,
+    #[id = "2+3+4"]
+    #[name = "2+3+4"]
+    TwoThreeFour,
+    #[id = "all"]
+    #[name = "1+2+3+4"]
+    All,
+}
+
+impl HeadSelect {
+    /// The heads this setting turns on, or `None` for `Custom`.
+    pub fn mask(&self) -> Option<[bool; NUM_HEADS]> {
+        match self {
+            HeadSelect::Custom => None,
+            HeadSelect::One => Some([true, false, false, false]),
+            HeadSelect::Two => Some([false, true, false, false]),
+            HeadSelect::Three => Some([false, false, true, false]),
+            HeadSelect::Four => Some([false, false, false, true]),
+            HeadSelect::OneTwo => Some([true, true, false, false]),
+            HeadSelect::TwoThree => Some([false, true, true, false]),
+            HeadSelect::ThreeFour => Some([false, false, true, true]),
+            HeadSelect::OneTwoThree => Some([true, true, true, false]),
+            HeadSelect::TwoThreeFour => Some([false, true, true, true]),
+            HeadSelect::All => Some([true, true, true, true]),
+        }
+    }
+}
+
+/// A playback head sitting `delay` seconds behind the record head.
+#[derive(Clone, Copy)]
+pub struct TAPEHEAD {
+    pub enabled: bool,
+    pub level: f32,
+    pub delay: f32,
+}
+
+impl Default for TAPEHEAD {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: 1.0,
+            delay: 0.25,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct TAPESTATE {
     samplerate: f32,
     length: f32,
+    //longest loop the buffer is sized for, in seconds
+    max_length: f32,
     speed: f32,
+    octave: f32,
+    //the octave asked for, held back until the loop wraps
+    next_octave: f32,
+    //one lane per channel, frame n of channel c lives at n * NUM_CHANNELS + c
     buffer: Vec<f32>,
-    pub current_sample_idx: usize,
+    //what was on the tape before the last clear, swapped back in by undo
+    undo_buffer: Vec<f32>,
+    //fractional read/write position in frames, current_frame is the whole part of this
+    playhead: f64,
+    pub current_frame: usize,
+    //where the playhead started and ended its last move, not wrapped, the writes cover the frames
+    //in between
+    span: (f64, f64),
+    //what each channel last wrote, the start of the line the next write is interpolated along
+    last_written: [f32; NUM_CHANNELS],
+    //how far the playhead moved on the last frame, negative in reverse
+    last_delta: f64,
+    //how much of what's already on the tape survives each pass of the record head
+    feedback: f32,
+    pub heads: [TAPEHEAD; NUM_HEADS],
+    //while frozen nothing is written, a freeze window of 0 keeps looping the whole tape
+    frozen: bool,
+    freeze_start: f64,
+    freeze_len: f64,
+    freeze_pos: f64,
 }
 
 impl Default for TAPESTATE {
@@ -17,53 +154,262 @@ impl Default for TAPESTATE {
         Self {
             samplerate: 44100.0,
             length: 6.0,
+            max_length: MAX_TAPE_LENGTH,
             speed: 1.0,
-            buffer: vec![0.0; 44100],
-            current_sample_idx: 0,
+            octave: 1.0,
+            next_octave: 1.0,
+            buffer: vec![0.0; 44100 * NUM_CHANNELS],
+            undo_buffer: vec![0.0; 44100 * NUM_CHANNELS],
+            playhead: 0.0,
+            current_frame: 0,
+            span: (0.0, 0.0),
+            last_written: [0.0; NUM_CHANNELS],
+            last_delta: 0.0,
+            feedback: 1.0,
+            heads: [TAPEHEAD::default(); NUM_HEADS],
+            frozen: false,
+            freeze_start: 0.0,
+            freeze_len: 0.0,
+            freeze_pos: 0.0,
         }
     }
 }
 
 impl TAPESTATE {
     pub fn init(&mut self, samplerate: f32) {
+        self.init_with_max_length(samplerate, MAX_TAPE_LENGTH);
+    }
+
+    /// Like `init`, for a tape that never needs to hold more than `max_length` seconds.
+    pub fn init_with_max_length(&mut self, samplerate: f32, max_length: f32) {
         self.samplerate = samplerate;
-        self.current_sample_idx = 0;
-        self.buffer = vec![0.0; (self.samplerate * MAX_TAPE_LENGTH) as usize];
+        self.max_length = f32::clamp(max_length, 1.0, MAX_TAPE_LENGTH);
+        self.length = self.length.min(self.max_length);
+        self.playhead = 0.0;
+        self.current_frame = 0;
+        self.span = (0.0, 0.0);
+        self.last_written = [0.0; NUM_CHANNELS];
+        self.buffer = vec![0.0; (self.samplerate * self.max_length) as usize * NUM_CHANNELS];
+        self.undo_buffer = vec![0.0; self.buffer.len()];
     }
 
-    pub fn inc_sample_idx(&mut self) {
-        if self.current_sample_idx >= self.end_of_loop() {
-            self.current_sample_idx = 0;
+    /// Move the tape on by one frame from the transport buttons. Reverse wins over fast forward,
+    /// which wins over play.
+    pub fn run_transport(&mut self, reverse: bool, fast_forward: bool, play: bool) {
+        if reverse {
+            self.dec_sample_idx();
+        } else if fast_forward {
+            self.fast_forward();
+        } else if play {
+            self.inc_sample_idx(); //play normally
         } else {
-            self.current_sample_idx += 1;
+            self.last_delta = 0.0;
+            self.span = (self.playhead, self.playhead);
         }
     }
 
+    pub fn inc_sample_idx(&mut self) {
+        self.move_playhead(self.playback_rate() as f64);
+    }
+
     pub fn dec_sample_idx(&mut self) {
-        if self.current_sample_idx == 0 {
-            self.current_sample_idx = self.end_of_loop();
+        self.move_playhead(-self.playback_rate() as f64);
+    }
+
+    pub fn fast_forward(&mut self) {
+        self.move_playhead(MAX_TAPE_SPEED as f64);
+    }
+
+    fn move_playhead(&mut self, delta: f64) {
+        self.last_delta = delta;
+
+        if self.frozen_window() {
+            self.freeze_pos = (self.freeze_pos + delta).rem_euclid(self.freeze_len);
+            self.span = (self.playhead, self.playhead);
+            return;
+        }
+
+        let loop_len = self.loop_length() as f64;
+
+        let moved = self.playhead + delta;
+        if moved < 0.0 || moved >= loop_len {
+            self.octave = self.next_octave;
+        }
+
+        self.span = (self.playhead, moved);
+        self.playhead = moved.rem_euclid(loop_len);
+        self.current_frame = (self.playhead as usize).min(self.end_of_loop());
+    }
+
+    /// Record `sample` onto `channel`'s lane. The head lays a line from the last sample to this one
+    /// over every frame it passed, so each frame gets written (and loses `feedback`) exactly once a
+    /// pass whatever the speed.
+    pub fn to_buffer(&mut self, channel: usize, sample: &mut f32, gain: Option<f32>) {
+        let feedback = self.feedback;
+        self.write_span(channel, *sample * gain.unwrap_or(1.0), |on_tape, new| {
+            on_tape * feedback + new
+        });
+    }
+
+    /// Record `sample` over whatever is on `channel`'s lane, nothing already on the tape survives.
+    pub fn print(&mut self, channel: usize, sample: f32) {
+        self.write_span(channel, sample, |_, new| new);
+    }
+
+    /// Run whatever is under the record head on `channel`'s lane through `filter`, once per pass.
+    /// Anything applied here builds up every time the loop comes back around.
+    pub fn filter_under_head(&mut self, channel: usize, mut filter: impl FnMut(f32) -> f32) {
+        if self.frozen {
+            return;
+        }
+
+        for (frame, _) in crossed_frames(self.span, self.loop_length() as f64) {
+            let idx = frame * NUM_CHANNELS + channel;
+            self.buffer[idx] = filter(self.buffer[idx]);
+        }
+    }
+
+    /// Echo mode write: the record head erases what was there and lays down the input plus the
+    /// heads' output scaled by the feedback, like a tape echo. The heads are averaged on the way
+    /// back so a pass never comes round louder than `feedback`, however many of them are on.
+    pub fn to_buffer_echo(
+        &mut self,
+        channel: usize,
+        sample: &mut f32,
+        gain: Option<f32>,
+        echo: f32,
+    ) {
+        let heads = self.heads.iter().filter(|head| head.enabled).count().max(1);
+        let recorded = *sample * gain.unwrap_or(1.0) + echo * self.feedback / heads as f32;
+        self.write_span(channel, recorded, |_, new| new);
+    }
+
+    fn write_span(&mut self, channel: usize, value: f32, write: impl Fn(f32, f32) -> f32) {
+        let last = self.last_written[channel];
+        self.last_written[channel] = value;
+
+        if self.frozen {
+            return;
+        }
+
+        for (frame, t) in crossed_frames(self.span, self.loop_length() as f64) {
+            let idx = frame * NUM_CHANNELS + channel;
+            self.buffer[idx] = write(self.buffer[idx], last + (value - last) * t);
+        }
+    }
+
+    /// Sum of all enabled playback heads on `channel`. While a freeze window is held the playhead
+    /// stands still, so the heads follow the position in the window instead.
+    pub fn echo(&self, channel: usize) -> f32 {
+        let position = match self.freeze_window() {
+            Some((start, _)) => start + self.freeze_pos,
+            None => self.playhead,
+        };
+
+        let mut out = 0.0;
+        for head in self.heads.iter().filter(|head| head.enabled) {
+            let offset = head.delay as f64 * self.samplerate as f64;
+            out += self.sample_at(position - offset, channel) * head.level;
+        }
+
+        return out;
+    }
+
+    pub fn from_buffer(&mut self, channel: usize) -> f32 {
+        if self.frozen_window() {
+            return self.freeze_window_sample(channel);
+        }
+
+        return self.sample_at(self.playhead, channel);
+    }
+
+    /// Hold whatever is on the tape. With a `window` (in seconds) the last stretch before the
+    /// freeze is looped on its own, otherwise the whole loop keeps going round untouched.
+    pub fn set_freeze(&mut self, freeze: bool, window: f32) {
+        if freeze && !self.frozen {
+            self.freeze_len = f64::min(
+                window as f64 * self.samplerate as f64,
+                self.loop_length() as f64,
+            );
+            self.freeze_start = self.playhead - self.freeze_len;
+            self.freeze_pos = 0.0;
+        }
+
+        self.frozen = freeze;
+    }
+
+    /// Start and length (in frames) of the freeze window, if there is one.
+    pub fn freeze_window(&self) -> Option<(f64, f64)> {
+        if self.frozen_window() {
+            Some((self.freeze_start, self.freeze_len))
         } else {
-            self.current_sample_idx -= 1;
+            None
         }
     }
 
-    pub fn fast_forward(&mut self) {
-        for _ in 0..MAX_TAPE_SPEED as i32 {
-            self.inc_sample_idx();
+    fn frozen_window(&self) -> bool {
+        self.frozen && self.freeze_len >= 1.0
+    }
+
+    //loop the freeze window, fading the end of it into the audio just before its start so the
+    //wrap is seamless
+    fn freeze_window_sample(&self, channel: usize) -> f32 {
+        let crossfade_len = self.freeze_len * FREEZE_CROSSFADE;
+        let crossfade_start = self.freeze_len - crossfade_len;
+
+        let current = self.sample_at(self.freeze_start + self.freeze_pos, channel);
+        if self.freeze_pos < crossfade_start {
+            return current;
         }
+
+        let t = ((self.freeze_pos - crossfade_start) / crossfade_len) as f32;
+        let lead_in = self.sample_at(
+            self.freeze_start + self.freeze_pos - self.freeze_len,
+            channel,
+        );
+
+        return current * (1.0 - t) + lead_in * t;
+    }
+
+    /// Read `channel`'s lane at an arbitrary (fractional) frame, wrapping around the loop
+    /// boundaries.
+    pub fn sample_at(&self, position: f64, channel: usize) -> f32 {
+        let loop_len = self.loop_length() as f64;
+        let position = position.rem_euclid(loop_len);
+
+        //rem_euclid can round up to loop_len itself for tiny negative positions
+        let idx = (position as usize).min(self.end_of_loop());
+        let next_idx = if idx >= self.end_of_loop() {
+            0
+        } else {
+            idx + 1
+        };
+        let frac = (position - idx as f64) as f32;
+
+        return self.buffer[idx * NUM_CHANNELS + channel] * (1.0 - frac)
+            + self.buffer[next_idx * NUM_CHANNELS + channel] * frac;
+    }
+
+    pub fn sample_under_head(&self, channel: usize) -> f32 {
+        self.buffer[self.current_frame * NUM_CHANNELS + channel]
     }
 
-    pub fn to_buffer(&mut self, sample: &mut f32, gain: Option<f32>) {
-        self.buffer[self.current_sample_idx] += sample.clone() * gain.unwrap_or(1.0);
+    pub fn playhead(&self) -> f64 {
+        self.playhead
     }
 
-    pub fn from_buffer(&mut self) -> f32 {
-        return self.buffer[self.current_sample_idx];
+    pub fn samplerate(&self) -> f32 {
+        self.samplerate
+    }
+
+    /// Number of frames in the current loop.
+    pub fn loop_length(&self) -> usize {
+        self.end_of_loop() + 1
     }
 
     pub fn set_tape_length(&mut self, len: f32) {
         let current_len = self.length;
-        let new_len = f32::clamp(len, 1.0, MAX_TAPE_LENGTH);
+        let new_len = f32::clamp(len, 1.0, self.max_length);
 
         self.length = new_len;
     }
@@ -72,19 +418,90 @@ impl TAPESTATE {
         self.speed = f32::clamp(speed, 0.1, MAX_TAPE_SPEED);
     }
 
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = f32::clamp(feedback, 0.0, 1.0);
+    }
+
+    pub fn set_speed_octave(&mut self, octave: SpeedOctave) {
+        self.next_octave = octave.ratio();
+
+        //nothing to stay in step with while the tape is stopped
+        if self.last_delta == 0.0 {
+            self.octave = self.next_octave;
+        }
+    }
+
+    fn playback_rate(&self) -> f32 {
+        self.speed * self.octave
+    }
+
+    /// Samples the tape can hold across all its lanes, for sizing anything that copies it.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Copy the loop out with its lanes interleaved, `out` should already have room for the whole
+    /// tape.
+    pub fn export(&self, out: &mut Vec<f32>) {
+        let len = (self.loop_length() * NUM_CHANNELS).min(out.capacity());
+        out.clear();
+        out.extend_from_slice(&self.buffer[..len]);
+    }
+
+    /// Lay interleaved `samples` down from the start of the tape and wipe the rest.
+    pub fn import(&mut self, samples: &[f32]) {
+        let len = samples.len().min(self.buffer.len());
+        self.buffer[..len].copy_from_slice(&samples[..len]);
+        self.buffer[len..].fill(0.0);
+        self.playhead = 0.0;
+        self.current_frame = 0;
+        self.span = (0.0, 0.0);
+    }
+
+    /// Wipe the tape, keeping what was on it for `undo`.
     pub fn clear(&mut self) {
+        std::mem::swap(&mut self.buffer, &mut self.undo_buffer);
         self.buffer.fill(0.0);
     }
 
-    pub fn current_position_percent(&mut self) -> f32 {
-        self.current_sample_idx as f32 / self.end_of_loop() as f32
+    /// Swap back whatever the last clear wiped, undoing again brings the cleared tape back.
+    pub fn undo(&mut self) {
+        std::mem::swap(&mut self.buffer, &mut self.undo_buffer);
     }
 
-    fn end_of_loop(&mut self) -> usize {
+    /// How many frames the tape moved on the last frame, which is also seconds of tape per second.
+    /// 1.0 at normal speed, negative in reverse and 0 when stopped.
+    pub fn velocity(&self) -> f32 {
+        self.last_delta as f32
+    }
+
+    pub fn current_position_percent(&self) -> f32 {
+        self.current_frame as f32 / self.end_of_loop() as f32
+    }
+
+    //the loop runs from frame 0 up to and including this one
+    fn end_of_loop(&self) -> usize {
         return f32::clamp(
             self.length * self.samplerate,
             self.samplerate,
-            (self.buffer.len() - 1) as f32,
+            (self.buffer.len() / NUM_CHANNELS - 1) as f32,
         ) as usize;
     }
 }
+
+//the whole frames the head went over between the two ends of `span`, in the order it passed them,
+//with how far along the move each one was. the frame it started on was covered by the move before
+fn crossed_frames(span: (f64, f64), loop_len: f64) -> impl Iterator<Item = (usize, f32)> {
+    let (start, end) = span;
+    let (first, count, step) = if end > start {
+        (start.floor() + 1.0, end.floor() - start.floor(), 1.0)
+    } else {
+        (start.ceil() - 1.0, start.ceil() - end.ceil(), -1.0)
+    };
+
+    (0..count as usize).map(move |i| {
+        let position = first + i as f64 * step;
+        let frame = (position.rem_euclid(loop_len) as usize).min(loop_len as usize - 1);
+        (frame, ((position - start) / (end - start)) as f32)
+    })
+}
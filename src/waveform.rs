@@ -0,0 +1,97 @@
+//overview of what's on the tape for the editor. the audio thread keeps a min/max per column of the
+//loop up to date as the record head passes over it, the editor just reads the atomics
+
+use crate::tapeloop::TAPESTATE;
+use crate::NUM_CHANNELS;
+use atomic_float::AtomicF32;
+use std::sync::atomic::Ordering;
+
+pub const WAVEFORM_COLUMNS: usize = 256;
+
+/// Per column min/max of the loop plus the freeze window, positions are fractions of the loop.
+pub struct WaveformData {
+    pub min: [AtomicF32; WAVEFORM_COLUMNS],
+    pub max: [AtomicF32; WAVEFORM_COLUMNS],
+    //negative when there's no freeze window
+    pub freeze_start: AtomicF32,
+    pub freeze_end: AtomicF32,
+}
+
+impl Default for WaveformData {
+    fn default() -> Self {
+        Self {
+            min: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            max: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            freeze_start: AtomicF32::new(-1.0),
+            freeze_end: AtomicF32::new(-1.0),
+        }
+    }
+}
+
+impl WaveformData {
+    pub fn clear(&self) {
+        for (min, max) in self.min.iter().zip(self.max.iter()) {
+            min.store(0.0, Ordering::Relaxed);
+            max.store(0.0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WAVEFORMSTATE {
+    last_column: usize,
+}
+
+impl Default for WAVEFORMSTATE {
+    fn default() -> Self {
+        Self {
+            last_column: usize::MAX,
+        }
+    }
+}
+
+impl WAVEFORMSTATE {
+    /// Fold whatever is under the record head, on every channel, into its column. Called once a
+    /// frame. A column starts over the first time the head lands in it so it always shows the
+    /// latest pass.
+    pub fn update(&mut self, tape: &TAPESTATE, data: &WaveformData) {
+        let column =
+            (tape.current_frame * WAVEFORM_COLUMNS / tape.loop_length()).min(WAVEFORM_COLUMNS - 1);
+
+        let mut min = f32::MAX;
+        let mut max = f32::MIN;
+        for channel in 0..NUM_CHANNELS {
+            let value = tape.sample_under_head(channel);
+            min = min.min(value);
+            max = max.max(value);
+        }
+
+        if column != self.last_column {
+            self.last_column = column;
+            data.min[column].store(min, Ordering::Relaxed);
+            data.max[column].store(max, Ordering::Relaxed);
+        } else {
+            //only the audio thread writes these so a plain load and store is fine
+            if min < data.min[column].load(Ordering::Relaxed) {
+                data.min[column].store(min, Ordering::Relaxed);
+            }
+            if max > data.max[column].load(Ordering::Relaxed) {
+                data.max[column].store(max, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn publish_markers(&self, tape: &TAPESTATE, data: &WaveformData) {
+        let (start, end) = match tape.freeze_window() {
+            Some((start, len)) => {
+                let loop_len = tape.loop_length() as f64;
+                let start = start.rem_euclid(loop_len) / loop_len;
+                (start as f32, (start + len / loop_len) as f32)
+            }
+            None => (-1.0, -1.0),
+        };
+
+        data.freeze_start.store(start, Ordering::Relaxed);
+        data.freeze_end.store(end, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,429 @@
+//custom egui widgets for the editor
+
+use crate::analyzer::{SpectrumAnalyzer, FFT_SIZE};
+use crate::meter::MeterData;
+use crate::waveform::{WaveformData, WAVEFORM_COLUMNS};
+use crate::NUM_CHANNELS;
+use nih_plug::prelude::{Param, ParamSetter};
+use nih_plug::util;
+use nih_plug_egui::egui;
+use std::f32::consts::PI;
+use std::sync::atomic::Ordering;
+
+//bottom of the meter scale, in dBFS
+const METER_FLOOR_DB: f32 = -60.0;
+const CLIP_LIGHT_WIDTH: f32 = 10.0;
+//knobs sweep 270 degrees, gap at the bottom
+const KNOB_SWEEP: f32 = 1.5 * PI;
+const KNOB_START: f32 = -0.5 * PI - KNOB_SWEEP * 0.5;
+//dragging this many diameters covers the whole range
+const KNOB_DRAG_DIAMETERS: f32 = 4.0;
+//how much finer dragging gets with shift held
+const KNOB_FINE_FACTOR: f32 = 0.1;
+//reel geometry as fractions of the reel's radius, an empty reel is just the hub
+const REEL_HUB_RADIUS: f32 = 0.25;
+const REEL_FULL_RADIUS: f32 = 0.48;
+//a half full reel turns at about half a revolution a second when the tape's velocity is 1.0,
+//normal speed
+const REEL_SPIN: f32 = PI * 0.37;
+//spectrum display range
+const SPECTRUM_MIN_HZ: f32 = 20.0;
+const SPECTRUM_MAX_HZ: f32 = 20000.0;
+const SPECTRUM_FLOOR_DB: f32 = -90.0;
+//the band gain overlay has its own scale around the middle of the display
+const BAND_GAIN_RANGE_DB: f32 = 12.0;
+
+fn meter_position(gain: f32) -> f32 {
+    let db = util::gain_to_db(gain);
+    ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+fn db_text(db: f32, unit: &str) -> String {
+    if db > util::MINUS_INFINITY_DB {
+        format!("{db:.1} {unit}")
+    } else {
+        format!("-inf {unit}")
+    }
+}
+
+/// Horizontal stereo meter: RMS as the solid bar, peak as the lighter bar on top of it, a peak hold
+/// line and a clip light on the end. Clicking the meter resets the clip light.
+pub fn level_meter(ui: &mut egui::Ui, rect: egui::Rect, data: &MeterData) -> egui::Response {
+    let response = ui.allocate_rect(rect, egui::Sense::click());
+    let painter = ui.painter_at(rect);
+
+    let bars_rect = egui::Rect::from_min_max(
+        rect.min,
+        egui::pos2(rect.max.x - CLIP_LIGHT_WIDTH - 2.0, rect.max.y),
+    );
+    let bar_height = bars_rect.height() / NUM_CHANNELS as f32;
+
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    let mut clipped = false;
+    for (channel, meter) in data.channels.iter().enumerate() {
+        let top = bars_rect.min.y + bar_height * channel as f32;
+        let bar = |level: f32| {
+            egui::Rect::from_min_size(
+                egui::pos2(bars_rect.min.x, top + 0.5),
+                egui::vec2(bars_rect.width() * meter_position(level), bar_height - 1.0),
+            )
+        };
+
+        let peak = meter.peak.load(Ordering::Relaxed);
+        let rms = meter.rms.load(Ordering::Relaxed);
+        let hold = meter.hold.load(Ordering::Relaxed);
+        clipped |= meter.clip.load(Ordering::Relaxed);
+
+        painter.rect_filled(bar(peak), 0.0, egui::Color32::from_rgb(120, 170, 110));
+        painter.rect_filled(bar(rms), 0.0, egui::Color32::from_rgb(70, 140, 60));
+
+        let hold_x = bars_rect.min.x + bars_rect.width() * meter_position(hold);
+        painter.line_segment(
+            [
+                egui::pos2(hold_x, top),
+                egui::pos2(hold_x, top + bar_height),
+            ],
+            egui::Stroke::new(1.0, egui::Color32::from_rgb(230, 220, 120)),
+        );
+    }
+
+    let clip_rect = egui::Rect::from_min_max(
+        egui::pos2(rect.max.x - CLIP_LIGHT_WIDTH, rect.min.y),
+        rect.max,
+    );
+    painter.rect_filled(
+        clip_rect,
+        2.0,
+        if clipped {
+            egui::Color32::from_rgb(220, 40, 30)
+        } else {
+            egui::Color32::from_rgb(60, 20, 20)
+        },
+    );
+
+    if response.clicked() {
+        data.reset_clip();
+    }
+
+    let peak_db = util::gain_to_db(
+        data.channels
+            .iter()
+            .map(|meter| meter.hold.load(Ordering::Relaxed))
+            .fold(0.0, f32::max),
+    );
+    let lufs = data.short_term_lufs.load(Ordering::Relaxed);
+
+    response.on_hover_text(format!(
+        "peak {}\nshort term {}\nclick to reset the clip light",
+        db_text(peak_db, "dBFS"),
+        db_text(lufs, "LUFS"),
+    ))
+}
+
+/// Rotary knob for any parameter, works in normalized values so it follows the parameter's own
+/// range and skew. Drag up and down to turn it, hold shift to fine tune, double click to reset.
+pub fn knob<P: Param>(
+    ui: &mut egui::Ui,
+    setter: &ParamSetter,
+    param: &P,
+    diameter: f32,
+) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(diameter, diameter),
+        egui::Sense::click_and_drag(),
+    );
+
+    //stepped params snap on every set, so the drag keeps its own unsnapped value
+    if response.drag_started() {
+        setter.begin_set_parameter(param);
+        ui.memory()
+            .data
+            .insert_temp(response.id, param.unmodulated_normalized_value());
+    }
+
+    if response.double_clicked() {
+        //a gesture of its own, the same as nih_plug's ParamSlider, so hosts record the reset
+        setter.begin_set_parameter(param);
+        setter.set_parameter_normalized(param, param.default_normalized_value());
+        setter.end_set_parameter(param);
+        ui.memory()
+            .data
+            .insert_temp(response.id, param.default_normalized_value());
+    } else if response.dragged() {
+        let mut delta = -response.drag_delta().y / (diameter * KNOB_DRAG_DIAMETERS);
+        if ui.input().modifiers.shift {
+            delta *= KNOB_FINE_FACTOR;
+        }
+
+        let start = ui
+            .memory()
+            .data
+            .get_temp::<f32>(response.id)
+            .unwrap_or_else(|| param.unmodulated_normalized_value());
+        let value = (start + delta).clamp(0.0, 1.0);
+
+        ui.memory().data.insert_temp(response.id, value);
+        setter.set_parameter_normalized(param, value);
+    }
+
+    if response.drag_released() {
+        setter.end_set_parameter(param);
+    }
+
+    //body, track, value arc and pointer
+    let painter = ui.painter_at(rect.expand(1.0));
+    let center = rect.center();
+    let radius = diameter * 0.5 - 1.0;
+    let at = |angle: f32, r: f32| center + egui::vec2(angle.cos(), angle.sin()) * r;
+    let arc = |from: f32, to: f32, r: f32| -> Vec<egui::Pos2> {
+        let steps = 32;
+        (0..=steps)
+            .map(|i| at(from + (to - from) * i as f32 / steps as f32, r))
+            .collect()
+    };
+
+    let value = param.unmodulated_normalized_value();
+    let angle = KNOB_START + KNOB_SWEEP * value;
+    let track_radius = radius - 1.5;
+
+    painter.circle_filled(
+        center,
+        radius * 0.72,
+        egui::Color32::from_rgb(200, 190, 170),
+    );
+    painter.circle_stroke(
+        center,
+        radius * 0.72,
+        egui::Stroke::new(1.0, egui::Color32::from_gray(40)),
+    );
+    painter.add(egui::Shape::line(
+        arc(KNOB_START, KNOB_START + KNOB_SWEEP, track_radius),
+        egui::Stroke::new(2.0, egui::Color32::from_gray(50)),
+    ));
+    painter.add(egui::Shape::line(
+        arc(KNOB_START, angle, track_radius),
+        egui::Stroke::new(2.0, egui::Color32::from_rgb(230, 150, 60)),
+    ));
+    painter.line_segment(
+        [at(angle, radius * 0.2), at(angle, radius * 0.65)],
+        egui::Stroke::new(2.0, egui::Color32::from_gray(30)),
+    );
+
+    response.on_hover_text(format!(
+        "{}: {}\ndouble click to reset, shift to fine tune",
+        param.name(),
+        param.normalized_value_to_string(value, true),
+    ))
+}
+
+/// A knob with a short label underneath.
+pub fn labelled_knob<P: Param>(
+    ui: &mut egui::Ui,
+    setter: &ParamSetter,
+    param: &P,
+    label: &str,
+    diameter: f32,
+) -> egui::Response {
+    ui.vertical_centered(|ui| {
+        ui.set_width(diameter * 1.6);
+        let response = knob(ui, setter, param, diameter);
+        ui.label(egui::RichText::new(label).small());
+        response
+    })
+    .inner
+}
+
+/// Radius of the tape pack as a fraction of the reel's, `pack` being how much of the loop is wound
+/// onto the reel. The wound area goes up with the amount of tape, not the radius.
+pub fn pack_radius(pack: f32) -> f32 {
+    let pack = pack.clamp(0.0, 1.0);
+    (REEL_HUB_RADIUS.powi(2) + pack * (REEL_FULL_RADIUS.powi(2) - REEL_HUB_RADIUS.powi(2))).sqrt()
+}
+
+/// How fast a reel turns in radians per second. The tape runs past the heads at `velocity`, in
+/// seconds of tape per second like `TAPESTATE::velocity`, so a fuller reel turns slower than a
+/// nearly empty one.
+pub fn reel_angular_velocity(velocity: f32, pack: f32) -> f32 {
+    velocity * REEL_SPIN / pack_radius(pack)
+}
+
+/// One reel: the tape pack as a disc with the reel image on top, turned to `angle`.
+pub fn reel(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    texture: &egui::TextureHandle,
+    angle: f32,
+    pack: f32,
+) -> egui::Response {
+    ui.painter().circle_filled(
+        rect.center(),
+        rect.width() * 0.5 * pack_radius(pack),
+        egui::Color32::from_rgb(58, 42, 34),
+    );
+
+    ui.put(
+        rect,
+        egui::Image::new(texture, rect.size()).rotate(angle, egui::vec2(0.5, 0.5)),
+    )
+}
+
+/// Overview of the loop: min/max per column with the freeze window shaded, the loop's start and
+/// end bracketed and the playhead on top. `playhead` is a fraction of the loop.
+pub fn waveform(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    data: &WaveformData,
+    playhead: f32,
+) -> egui::Response {
+    let response = ui.allocate_rect(rect, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let x_at = |fraction: f32| rect.min.x + rect.width() * fraction;
+
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    //freeze window, which can wrap past the end of the loop
+    let freeze_start = data.freeze_start.load(Ordering::Relaxed);
+    let freeze_end = data.freeze_end.load(Ordering::Relaxed);
+    if freeze_start >= 0.0 {
+        let freeze_fill = egui::Color32::from_rgba_unmultiplied(90, 150, 220, 60);
+        for (start, end) in [(freeze_start, freeze_end.min(1.0)), (0.0, freeze_end - 1.0)] {
+            if end > start {
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges(x_at(start)..=x_at(end), rect.y_range()),
+                    0.0,
+                    freeze_fill,
+                );
+            }
+        }
+    }
+
+    let column_width = rect.width() / WAVEFORM_COLUMNS as f32;
+    let wave_stroke = egui::Stroke::new(column_width.max(1.0), egui::Color32::from_gray(180));
+    for (column, (min, max)) in data.min.iter().zip(data.max.iter()).enumerate() {
+        let x = rect.min.x + column_width * (column as f32 + 0.5);
+        let min = min.load(Ordering::Relaxed).clamp(-1.0, 1.0);
+        let max = max.load(Ordering::Relaxed).clamp(-1.0, 1.0);
+
+        painter.line_segment(
+            [
+                egui::pos2(x, rect.center().y - max * rect.height() * 0.5),
+                egui::pos2(x, rect.center().y - min * rect.height() * 0.5 + 0.5),
+            ],
+            wave_stroke,
+        );
+    }
+
+    //loop start and end
+    let marker_stroke = egui::Stroke::new(2.0, egui::Color32::from_rgb(230, 220, 120));
+    for x in [rect.min.x + 1.0, rect.max.x - 1.0] {
+        painter.line_segment(
+            [egui::pos2(x, rect.min.y), egui::pos2(x, rect.max.y)],
+            marker_stroke,
+        );
+    }
+
+    painter.line_segment(
+        [
+            egui::pos2(x_at(playhead), rect.min.y),
+            egui::pos2(x_at(playhead), rect.max.y),
+        ],
+        egui::Stroke::new(1.0, egui::Color32::from_rgb(220, 40, 30)),
+    );
+
+    response
+}
+
+fn spectrum_line(
+    rect: egui::Rect,
+    spectrum_db: &[f32],
+    sample_rate: f32,
+    x_at: impl Fn(f32) -> f32,
+) -> Vec<egui::Pos2> {
+    let bin_hz = sample_rate / FFT_SIZE as f32;
+
+    //bin 0 is DC and has no place on a log axis
+    spectrum_db
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(bin, db)| (bin as f32 * bin_hz, db))
+        .filter(|(freq, _)| (SPECTRUM_MIN_HZ..=SPECTRUM_MAX_HZ).contains(freq))
+        .map(|(freq, db)| {
+            let level = ((db - SPECTRUM_FLOOR_DB) / -SPECTRUM_FLOOR_DB).clamp(0.0, 1.0);
+            egui::pos2(x_at(freq), rect.max.y - rect.height() * level)
+        })
+        .collect()
+}
+
+/// Input and tape return spectra on a log frequency axis, with the three band EQ's crossover
+/// points and band gains drawn over the top. `band_gains` are linear, low to high.
+pub fn spectrum(
+    ui: &mut egui::Ui,
+    rect: egui::Rect,
+    analyzer: &SpectrumAnalyzer,
+    sample_rate: f32,
+    crossovers: (f32, f32),
+    band_gains: [f32; 3],
+) -> egui::Response {
+    let response = ui.allocate_rect(rect, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let log_range = (SPECTRUM_MAX_HZ / SPECTRUM_MIN_HZ).ln();
+    let x_at = |freq: f32| {
+        rect.min.x + rect.width() * ((freq / SPECTRUM_MIN_HZ).ln() / log_range).clamp(0.0, 1.0)
+    };
+
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+    //decade grid
+    let grid_stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(45));
+    for freq in [100.0, 1000.0, 10000.0] {
+        painter.line_segment(
+            [
+                egui::pos2(x_at(freq), rect.min.y),
+                egui::pos2(x_at(freq), rect.max.y),
+            ],
+            grid_stroke,
+        );
+    }
+
+    painter.add(egui::Shape::line(
+        spectrum_line(rect, &analyzer.input_db, sample_rate, x_at),
+        egui::Stroke::new(1.0, egui::Color32::from_gray(150)),
+    ));
+    painter.add(egui::Shape::line(
+        spectrum_line(rect, &analyzer.tape_return_db, sample_rate, x_at),
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(230, 150, 60)),
+    ));
+
+    //crossovers
+    let (low, high) = crossovers;
+    let crossover_stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(90, 150, 220));
+    for freq in [low, high] {
+        painter.line_segment(
+            [
+                egui::pos2(x_at(freq), rect.min.y),
+                egui::pos2(x_at(freq), rect.max.y),
+            ],
+            crossover_stroke,
+        );
+    }
+
+    //band gains
+    let gain_stroke = egui::Stroke::new(2.0, egui::Color32::from_rgb(230, 220, 120));
+    let band_edges = [SPECTRUM_MIN_HZ, low, high, SPECTRUM_MAX_HZ];
+    for (band, gain) in band_gains.iter().enumerate() {
+        let db = util::gain_to_db(*gain).clamp(-BAND_GAIN_RANGE_DB, BAND_GAIN_RANGE_DB);
+        let y = rect.center().y - db / BAND_GAIN_RANGE_DB * rect.height() * 0.5;
+
+        painter.line_segment(
+            [
+                egui::pos2(x_at(band_edges[band]), y),
+                egui::pos2(x_at(band_edges[band + 1]), y),
+            ],
+            gain_stroke,
+        );
+    }
+
+    response.on_hover_text("grey: input\norange: tape return\nblue: crossovers\nyellow: band gains")
+}